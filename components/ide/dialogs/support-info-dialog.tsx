@@ -333,6 +333,33 @@ export function SupportInfoDialog({ open, onClose }: SupportInfoDialogProps) {
 									)}
 								</div>
 							</Section>
+
+							{/* Shell Log */}
+							<Section title="App Log">
+								<div className="space-y-2">
+									<KeyValue
+										label="Log Path"
+										value={supportInfo.shell_log_path}
+									/>
+									{supportInfo.shell_log_exists && supportInfo.shell_log && (
+										<div className="mt-2">
+											<pre className="text-xs bg-muted p-3 rounded-md overflow-x-auto max-h-60 overflow-y-auto font-mono">
+												{supportInfo.shell_log}
+											</pre>
+										</div>
+									)}
+									{supportInfo.shell_log_exists && !supportInfo.shell_log && (
+										<p className="text-sm text-muted-foreground">
+											Log file is empty
+										</p>
+									)}
+									{!supportInfo.shell_log_exists && (
+										<p className="text-sm text-muted-foreground">
+											Log file not found
+										</p>
+									)}
+								</div>
+							</Section>
 						</div>
 					)}
 				</div>
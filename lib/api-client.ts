@@ -1,5 +1,10 @@
 // API Client for making requests to the backend
-import { appendAuthToken, getApiBase, getApiRootBase } from "./api-config";
+import {
+	appendAuthToken,
+	getApiBase,
+	getRequestApiBase,
+	getRequestApiRootBase,
+} from "./api-config";
 
 /** Error thrown when file write fails due to optimistic locking conflict */
 export class FileConflictError extends Error {
@@ -72,12 +77,15 @@ class ApiClient {
 	private get base() {
 		return getApiBase();
 	}
-	private get rootBase() {
-		return getApiRootBase();
+	private get requestBase() {
+		return getRequestApiBase();
+	}
+	private get requestRootBase() {
+		return getRequestApiRootBase();
 	}
 
 	private async fetch<T>(path: string, options?: RequestInit): Promise<T> {
-		const response = await fetch(appendAuthToken(`${this.base}${path}`), {
+		const response = await fetch(`${this.requestBase}${path}`, {
 			...options,
 			headers: {
 				"Content-Type": "application/json",
@@ -102,7 +110,7 @@ class ApiClient {
 
 	// Fetch from root API (not project-scoped)
 	private async fetchRoot<T>(path: string, options?: RequestInit): Promise<T> {
-		const response = await fetch(appendAuthToken(`${this.rootBase}${path}`), {
+		const response = await fetch(`${this.requestRootBase}${path}`, {
 			...options,
 			headers: {
 				"Content-Type": "application/json",
@@ -257,7 +265,7 @@ class ApiClient {
 		data: WriteSessionFileRequest,
 	): Promise<WriteSessionFileResponse> {
 		const response = await fetch(
-			`${this.base}/sessions/${sessionId}/files/write`,
+			`${this.requestBase}/sessions/${sessionId}/files/write`,
 			{
 				method: "PUT",
 				headers: { "Content-Type": "application/json" },
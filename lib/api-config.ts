@@ -5,7 +5,11 @@ const DEFAULT_SSH_PORT = 3333;
 const tauriLocalhost = "localhost";
 
 // Cached Tauri server config (populated on first use)
-let tauriServerConfig: { port: number; secret: string } | null = null;
+let tauriServerConfig: {
+	port: number;
+	secret: string;
+	proxyBase: string;
+} | null = null;
 let tauriInitialized = false;
 
 // Server config (fetched from backend)
@@ -22,12 +26,29 @@ export async function initTauriConfig(): Promise<void> {
 	}
 
 	const { invoke } = await import("@tauri-apps/api/core");
-	const [port, secret] = await Promise.all([
+	const [port, secret, proxyBase] = await Promise.all([
 		invoke<number>("get_server_port"),
-		invoke<string>("get_server_secret"),
+		// Only needed for streaming endpoints, which fail without it
+		invoke<string>("get_server_secret").catch((error) => {
+			console.error(
+				"Failed to get the server secret; chat, events and the terminal won't connect:",
+				error,
+			);
+			return "";
+		}),
+		invoke<string>("get_api_proxy_url"),
 	]);
 	tauriInitialized = true;
-	tauriServerConfig = { port, secret };
+	tauriServerConfig = { port, secret, proxyBase };
+
+	// The secret can only be fetched again after Rust rotates it
+	const { listen } = await import("@tauri-apps/api/event");
+	await listen("secret-rotated", async () => {
+		const secret = await invoke<string>("get_server_secret");
+		if (tauriServerConfig) {
+			tauriServerConfig = { ...tauriServerConfig, secret };
+		}
+	});
 }
 
 /**
@@ -106,6 +127,27 @@ export function getApiBase() {
 	return `${getApiRootBase()}/projects/${PROJECT_ID}`;
 }
 
+/**
+ * Get the API root URL for plain request/response calls.
+ *
+ * In Tauri these go through the discobot-api:// scheme, which adds the auth
+ * secret natively. Streaming endpoints (SSE, WebSocket, chat) can't be
+ * proxied that way and keep using getApiRootBase() with appendAuthToken().
+ */
+export function getRequestApiRootBase() {
+	if (tauriServerConfig) {
+		return `${tauriServerConfig.proxyBase}/api`;
+	}
+	return getApiRootBase();
+}
+
+/**
+ * Get the API base URL (with project path) for plain request/response calls.
+ */
+export function getRequestApiBase() {
+	return `${getRequestApiRootBase()}/projects/${PROJECT_ID}`;
+}
+
 /**
  * Get the backend WebSocket base URL.
  * Includes auth token in Tauri mode.
@@ -125,9 +167,7 @@ export function getWsBase() {
  */
 export async function initServerConfig(): Promise<void> {
 	try {
-		const resp = await fetch(
-			appendAuthToken(`${getApiRootBase()}/server-config`),
-		);
+		const resp = await fetch(`${getRequestApiRootBase()}/server-config`);
 		if (resp.ok) {
 			const config = await resp.json();
 			if (typeof config.ssh_port === "number" && config.ssh_port > 0) {
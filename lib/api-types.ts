@@ -460,6 +460,10 @@ export interface SupportInfoResponse {
 	server_log: string;
 	log_path: string;
 	log_exists: boolean;
+	/** The desktop app's own log, next to the server's */
+	shell_log: string;
+	shell_log_path: string;
+	shell_log_exists: boolean;
 	system_info: SystemStatusResponse;
 }
 
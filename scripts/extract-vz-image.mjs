@@ -14,7 +14,16 @@
  */
 
 import { execSync } from "node:child_process";
-import { mkdirSync, statSync, unlinkSync } from "node:fs";
+import { createHash } from "node:crypto";
+import {
+	closeSync,
+	mkdirSync,
+	openSync,
+	readSync,
+	statSync,
+	unlinkSync,
+	writeFileSync,
+} from "node:fs";
 import { dirname, join } from "node:path";
 import { fileURLToPath } from "node:url";
 
@@ -22,6 +31,29 @@ const __dirname = dirname(fileURLToPath(import.meta.url));
 const projectRoot = join(__dirname, "..");
 const resourcesDir = join(projectRoot, "src-tauri", "resources");
 
+// Files whose digests are recorded in manifest.json; the desktop app verifies
+// them before passing them to the server.
+const verifiedFiles = ["vmlinux", "discobot-rootfs.squashfs"];
+
+/**
+ * Compute the hex SHA-256 of a file without loading it all into memory.
+ */
+function sha256File(filePath) {
+	const hash = createHash("sha256");
+	const buffer = Buffer.alloc(1024 * 1024);
+	const fd = openSync(filePath, "r");
+	try {
+		let bytesRead = readSync(fd, buffer, 0, buffer.length, null);
+		while (bytesRead > 0) {
+			hash.update(buffer.subarray(0, bytesRead));
+			bytesRead = readSync(fd, buffer, 0, buffer.length, null);
+		}
+	} finally {
+		closeSync(fd);
+	}
+	return hash.digest("hex");
+}
+
 // Parse arguments
 const imageRef = process.argv[2] || "ghcr.io/obot-platform/discobot-vz:main";
 const arch = process.argv[3] || (process.arch === "arm64" ? "arm64" : "amd64");
@@ -59,6 +91,21 @@ try {
 		unlinkSync(vmlinuzPath);
 	}
 
+	// Record checksums so the app can detect corrupted or partial files
+	const manifest = {};
+	for (const file of verifiedFiles) {
+		const filePath = join(resourcesDir, file);
+		try {
+			manifest[file] = sha256File(filePath);
+		} catch {
+			// vmlinux only exists on arm64; the app only uses verified files there
+		}
+	}
+	writeFileSync(
+		join(resourcesDir, "manifest.json"),
+		`${JSON.stringify(manifest, null, 2)}\n`,
+	);
+
 	console.log("VZ image files extracted successfully:");
 	for (const file of outputFiles) {
 		const filePath = join(resourcesDir, file);
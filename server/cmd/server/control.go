@@ -0,0 +1,106 @@
+package main
+
+import (
+	"bufio"
+	"bytes"
+	"encoding/json"
+	"fmt"
+	"io"
+	"log"
+	"log/slog"
+	"os"
+	"runtime"
+	"runtime/debug"
+	"runtime/pprof"
+
+	"github.com/obot-platform/discobot/server/internal/version"
+)
+
+// controlReplyPrefix marks stdout lines that answer a control command, so the
+// desktop app can tell them apart from log output.
+const controlReplyPrefix = "@control "
+
+// controlCommand is one newline-delimited JSON command read from stdin.
+type controlCommand struct {
+	Cmd   string `json:"cmd"`
+	ID    string `json:"id,omitempty"`
+	Level string `json:"level,omitempty"`
+}
+
+// controlReply answers a controlCommand on stdout, echoing its cmd and id.
+type controlReply struct {
+	Cmd    string `json:"cmd,omitempty"`
+	ID     string `json:"id,omitempty"`
+	OK     bool   `json:"ok"`
+	Error  string `json:"error,omitempty"`
+	Result any    `json:"result,omitempty"`
+}
+
+// readControlCommands handles the desktop app's commands from r, one JSON
+// object per line, until r is closed or fails.
+func readControlCommands(r io.Reader) {
+	reader := bufio.NewReader(r)
+	for {
+		line, err := reader.ReadBytes('\n')
+		if line = bytes.TrimSpace(line); len(line) > 0 {
+			var cmd controlCommand
+			if jsonErr := json.Unmarshal(line, &cmd); jsonErr != nil {
+				writeControlReply(controlReply{Error: fmt.Sprintf("invalid command: %v", jsonErr)})
+			} else {
+				writeControlReply(handleControlCommand(cmd))
+			}
+		}
+		if err != nil {
+			return
+		}
+	}
+}
+
+func handleControlCommand(cmd controlCommand) controlReply {
+	reply := controlReply{Cmd: cmd.Cmd, ID: cmd.ID, OK: true}
+	switch cmd.Cmd {
+	case "status":
+		var mem runtime.MemStats
+		runtime.ReadMemStats(&mem)
+		reply.Result = map[string]any{
+			"version":    version.Get(),
+			"goroutines": runtime.NumGoroutine(),
+			"heap_alloc": mem.HeapAlloc,
+		}
+	case "gc":
+		// Runs a collection and returns as much memory to the OS as possible
+		debug.FreeOSMemory()
+		var mem runtime.MemStats
+		runtime.ReadMemStats(&mem)
+		reply.Result = map[string]any{"heap_alloc": mem.HeapAlloc}
+	case "dump_goroutines":
+		// Stacks go to stderr, which the desktop app writes to the server log
+		if err := pprof.Lookup("goroutine").WriteTo(os.Stderr, 2); err != nil {
+			reply.OK, reply.Error = false, err.Error()
+		}
+		reply.Result = map[string]any{"goroutines": runtime.NumGoroutine()}
+	case "set_log_level":
+		// Takes effect right away; DISCOBOT_LOG_LEVEL carries it to the next start
+		var level slog.Level
+		if err := level.UnmarshalText([]byte(cmd.Level)); err != nil {
+			reply.OK, reply.Error = false, fmt.Sprintf("invalid log level %q", cmd.Level)
+			break
+		}
+		slog.SetLogLoggerLevel(level)
+		log.Printf("Log level set to %s", level)
+		reply.Result = map[string]any{"level": level.String()}
+	default:
+		reply.OK, reply.Error = false, fmt.Sprintf("unknown command %q", cmd.Cmd)
+	}
+	return reply
+}
+
+func writeControlReply(reply controlReply) {
+	data, err := json.Marshal(reply)
+	if err != nil {
+		log.Printf("Warning: failed to encode control reply: %v", err)
+		return
+	}
+	// One write per line keeps replies whole next to other stdout output
+	_, _ = os.Stdout.Write(append([]byte(controlReplyPrefix), append(data, '\n')...))
+}
@@ -5,10 +5,12 @@ import (
 	"fmt"
 	"log"
 	"log/slog"
+	"net"
 	"net/http"
 	"os"
 	"os/signal"
 	"runtime"
+	"strconv"
 	"syscall"
 	"time"
 
@@ -61,6 +63,14 @@ func main() {
 		}
 	}
 
+	// Apply the configured level to the default slog logger
+	var logLevel slog.Level
+	if err := logLevel.UnmarshalText([]byte(cfg.LogLevel)); err != nil {
+		log.Printf("Warning: invalid log level %q, using info", cfg.LogLevel)
+	} else {
+		slog.SetLogLoggerLevel(logLevel)
+	}
+
 	// Log version
 	log.Printf("Discobot Server version %s", version.Get())
 
@@ -234,16 +244,33 @@ func main() {
 
 	// Start SSH server for VS Code Remote SSH and other SSH-based workflows
 	var sshServer *ssh.Server
+	sshListener, err := inheritedListener("SSH_LISTEN_FD")
+	if err != nil {
+		log.Printf("Warning: %v, binding SSH port %d instead", err, cfg.SSHPort)
+	}
 	if sandboxProvider != nil && cfg.SSHEnabled {
+		if sshListener == nil {
+			sshListener, err = net.Listen("tcp", fmt.Sprintf(":%d", cfg.SSHPort))
+			if err != nil {
+				log.Printf("Warning: Failed to listen on SSH port %d: %v", cfg.SSHPort, err)
+			}
+		}
+	} else if sshListener != nil {
+		// Release the port the desktop app reserved for us
+		sshListener.Close()
+		sshListener = nil
+	}
+	if sshListener != nil {
 		// Create sandbox service for UserInfoFetcher
 		sshSandboxSvc := service.NewSandboxService(s, sandboxProvider, cfg, nil, nil, nil)
 		sshServer, err = ssh.New(&ssh.Config{
-			Address:         fmt.Sprintf(":%d", cfg.SSHPort),
+			Listener:        sshListener,
 			HostKeyPath:     cfg.SSHHostKeyPath,
 			SandboxProvider: sandboxProvider,
 			UserInfoFetcher: &sshUserInfoAdapter{svc: sshSandboxSvc},
 		})
 		if err != nil {
+			sshListener.Close()
 			log.Printf("Warning: Failed to create SSH server: %v", err)
 		} else {
 			go func() {
@@ -251,7 +278,10 @@ func main() {
 					log.Printf("SSH server stopped: %v", err)
 				}
 			}()
-			log.Printf("SSH server started on port %d", cfg.SSHPort)
+			sshPort := sshListener.Addr().(*net.TCPAddr).Port
+			log.Printf("SSH server started on port %d", sshPort)
+			// Machine-readable line the desktop app uses to confirm the SSH port
+			fmt.Printf("DISCOBOT_SSH_LISTENING port=%d\n", sshPort)
 		}
 	}
 
@@ -396,6 +426,12 @@ func main() {
 		Meta: routes.Meta{Group: "Health", Description: "Health check"},
 	})
 
+	reg.Register(r, routes.Route{
+		Method: "GET", Pattern: "/version",
+		Handler: h.GetVersion,
+		Meta:    routes.Meta{Group: "Health", Description: "Server version and build commit"},
+	})
+
 	reg.Register(r, routes.Route{
 		Method: "GET", Pattern: "/api/status",
 		Handler: h.GetSystemStatus,
@@ -408,6 +444,18 @@ func main() {
 		Meta:    routes.Meta{Group: "Health", Description: "Public server configuration (SSH port, etc.)"},
 	})
 
+	reg.Register(r, routes.Route{
+		Method: "GET", Pattern: "/api/active-tasks",
+		Handler: h.GetActiveTasks,
+		Meta:    routes.Meta{Group: "Health", Description: "Number of sessions with work in progress"},
+	})
+
+	reg.Register(r, routes.Route{
+		Method: "POST", Pattern: "/api/control",
+		Handler: h.Control,
+		Meta:    routes.Meta{Group: "Health", Description: "Run a maintenance action (gc, status, reload)"},
+	})
+
 	reg.Register(r, routes.Route{
 		Method: "GET", Pattern: "/api/support-info",
 		Handler: h.GetSupportInfo,
@@ -1465,11 +1513,46 @@ func main() {
 		Addr:    fmt.Sprintf(":%d", cfg.Port),
 		Handler: r,
 	}
+	if cfg.TauriMode {
+		// The desktop app's server is only for the local machine
+		srv.Addr = fmt.Sprintf("127.0.0.1:%d", cfg.Port)
+	}
+
+	// Bind before serving so the actual port is known when PORT=0
+	var listener net.Listener
+	socketPath := os.Getenv("LISTEN_SOCKET")
+	if socketPath != "" {
+		listener, err = listenUnix(socketPath)
+		if err != nil {
+			log.Fatalf("Failed to listen on %s: %v", socketPath, err)
+		}
+	} else {
+		listener, err = inheritedListener("LISTEN_FD")
+		if err != nil {
+			log.Printf("Warning: %v, binding port %d instead", err, cfg.Port)
+		}
+		if listener == nil {
+			listener, err = net.Listen("tcp", srv.Addr)
+			if err != nil {
+				log.Fatalf("Failed to listen on port %d: %v", cfg.Port, err)
+			}
+		}
+	}
 
 	// Start server in a goroutine
 	go func() {
-		log.Printf("Server starting on port %d", cfg.Port)
-		if err := srv.ListenAndServe(); err != nil && err != http.ErrServerClosed {
+		// Machine-readable lines the desktop app waits for
+		if socketPath != "" {
+			log.Printf("Server starting on %s", socketPath)
+			fmt.Printf("DISCOBOT_LISTENING socket=%s\n", socketPath)
+		} else {
+			port := listener.Addr().(*net.TCPAddr).Port
+			log.Printf("Server starting on port %d", port)
+			// Lets the desktop app check the server isn't exposed beyond localhost
+			fmt.Printf("DISCOBOT_LISTENING_ADDR addr=%s\n", listener.Addr())
+			fmt.Printf("DISCOBOT_LISTENING port=%d\n", port)
+		}
+		if err := srv.Serve(listener); err != nil && err != http.ErrServerClosed {
 			log.Fatalf("Server failed: %v", err)
 		}
 	}()
@@ -1478,17 +1561,15 @@ func main() {
 	quit := make(chan os.Signal, 1)
 	signal.Notify(quit, syscall.SIGINT, syscall.SIGTERM)
 
-	if cfg.StdinKeepalive {
+	if cfg.StdinKeepalive || cfg.TauriMode {
 		go func() {
-			// Block reading stdin; when the parent process dies, the pipe
-			// breaks and Read returns. Send SIGTERM to trigger graceful shutdown.
-			buf := make([]byte, 1)
-			for {
-				if _, err := os.Stdin.Read(buf); err != nil {
-					log.Println("Stdin closed, shutting down (parent process died)")
-					quit <- syscall.SIGTERM
-					return
-				}
+			// The desktop app sends control commands over stdin. When the
+			// parent process dies, the pipe breaks and reading returns; with
+			// STDIN_KEEPALIVE, send SIGTERM to trigger graceful shutdown.
+			readControlCommands(os.Stdin)
+			if cfg.StdinKeepalive {
+				log.Println("Stdin closed, shutting down (parent process died)")
+				quit <- syscall.SIGTERM
 			}
 		}()
 	}
@@ -1584,3 +1665,45 @@ func (a *sshUserInfoAdapter) GetUserInfo(ctx context.Context, sessionID string)
 	}
 	return userInfo.Username, userInfo.UID, userInfo.GID, nil
 }
+
+// listenUnix listens on a Unix domain socket at path, replacing a stale socket
+// left by a previous run. Only the current user may connect.
+func listenUnix(path string) (net.Listener, error) {
+	if err := os.Remove(path); err != nil && !os.IsNotExist(err) {
+		return nil, fmt.Errorf("failed to remove stale socket: %w", err)
+	}
+	listener, err := net.Listen("unix", path)
+	if err != nil {
+		return nil, err
+	}
+	if err := os.Chmod(path, 0o600); err != nil {
+		listener.Close()
+		return nil, fmt.Errorf("failed to restrict socket permissions: %w", err)
+	}
+	return listener, nil
+}
+
+// inheritedListener returns a socket the desktop app bound for us and passed
+// as the file descriptor named by the env var, so no other process can take
+// the port between the app choosing it and the server starting. Returns nil if
+// the variable is unset.
+func inheritedListener(env string) (net.Listener, error) {
+	value := os.Getenv(env)
+	if value == "" {
+		return nil, nil
+	}
+	fd, err := strconv.Atoi(value)
+	if err != nil {
+		return nil, fmt.Errorf("invalid %s %q", env, value)
+	}
+	file := os.NewFile(uintptr(fd), "listener")
+	if file == nil {
+		return nil, fmt.Errorf("%s %d is not a valid file descriptor", env, fd)
+	}
+	defer file.Close()
+	listener, err := net.FileListener(file)
+	if err != nil {
+		return nil, fmt.Errorf("failed to use %s %d: %w", env, fd, err)
+	}
+	return listener, nil
+}
@@ -109,6 +109,8 @@ type Config struct {
 	// Process lifecycle
 	LogFile        string // Redirect stdout/stderr to this file (Unix only)
 	StdinKeepalive bool   // Exit when stdin is closed (for parent process death detection)
+	LogLevel       string // Minimum slog level: debug, info, warn or error (default: info)
+	LogDir         string // Where the Tauri app writes the server's logs (DISCOBOT_LOG_DIR)
 
 	// Tauri mode settings
 	TauriMode   bool   // Running inside Tauri app (TAURI=true)
@@ -227,6 +229,8 @@ func Load() (*Config, error) {
 	// Process lifecycle
 	cfg.LogFile = getEnv("LOG_FILE", "")
 	cfg.StdinKeepalive = getEnvBool("STDIN_KEEPALIVE", false)
+	cfg.LogLevel = getEnv("DISCOBOT_LOG_LEVEL", getEnv("LOG_LEVEL", "info"))
+	cfg.LogDir = getEnv("DISCOBOT_LOG_DIR", filepath.Join(xdg.StateHome, appName, "logs"))
 
 	// Tauri mode settings
 	cfg.TauriMode = getEnvBool("TAURI", false)
@@ -0,0 +1,56 @@
+package handler
+
+import (
+	"fmt"
+	"net/http"
+	"runtime"
+	"runtime/debug"
+)
+
+// ControlRequest is a maintenance action sent by the desktop app
+type ControlRequest struct {
+	Action string `json:"action"`
+}
+
+// ControlStatusResponse describes the server process's resource use
+type ControlStatusResponse struct {
+	Goroutines     int    `json:"goroutines"`
+	HeapAllocBytes uint64 `json:"heap_alloc_bytes"`
+	SysBytes       uint64 `json:"sys_bytes"`
+	NumGC          uint32 `json:"num_gc"`
+}
+
+func controlStatus() ControlStatusResponse {
+	var mem runtime.MemStats
+	runtime.ReadMemStats(&mem)
+	return ControlStatusResponse{
+		Goroutines:     runtime.NumGoroutine(),
+		HeapAllocBytes: mem.HeapAlloc,
+		SysBytes:       mem.Sys,
+		NumGC:          mem.NumGC,
+	}
+}
+
+// Control runs a maintenance action without restarting the server.
+// "gc" returns unused memory to the OS and "status" reports resource use;
+// both reply with the resulting status. "reload" is reserved for reloading
+// configuration, which the server can't do yet.
+func (h *Handler) Control(w http.ResponseWriter, r *http.Request) {
+	var req ControlRequest
+	if err := h.DecodeJSON(r, &req); err != nil {
+		h.Error(w, http.StatusBadRequest, "Invalid request body")
+		return
+	}
+
+	switch req.Action {
+	case "gc":
+		debug.FreeOSMemory()
+		h.JSON(w, http.StatusOK, controlStatus())
+	case "status":
+		h.JSON(w, http.StatusOK, controlStatus())
+	case "reload":
+		h.Error(w, http.StatusNotImplemented, "Reloading configuration is not supported")
+	default:
+		h.Error(w, http.StatusBadRequest, fmt.Sprintf("Unknown control action %q", req.Action))
+	}
+}
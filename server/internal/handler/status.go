@@ -6,8 +6,7 @@ import (
 	"path/filepath"
 	"runtime"
 
-	"github.com/adrg/xdg"
-
+	"github.com/obot-platform/discobot/server/internal/model"
 	"github.com/obot-platform/discobot/server/internal/startup"
 	"github.com/obot-platform/discobot/server/internal/version"
 )
@@ -24,6 +23,35 @@ func (h *Handler) GetServerConfig(w http.ResponseWriter, _ *http.Request) {
 	})
 }
 
+// VersionResponse identifies the server build
+type VersionResponse struct {
+	Version string `json:"version"`
+	Commit  string `json:"commit,omitempty"`
+}
+
+// GetVersion returns the server version and the commit it was built from
+func (h *Handler) GetVersion(w http.ResponseWriter, _ *http.Request) {
+	h.JSON(w, http.StatusOK, VersionResponse{
+		Version: version.Get(),
+		Commit:  version.Commit(),
+	})
+}
+
+// ActiveTasksResponse reports work that would be interrupted by stopping the server
+type ActiveTasksResponse struct {
+	Count int `json:"count"`
+}
+
+// GetActiveTasks returns the number of sessions with a chat completion in progress
+func (h *Handler) GetActiveTasks(w http.ResponseWriter, r *http.Request) {
+	sessions, err := h.store.ListSessionsByStatuses(r.Context(), []string{model.SessionStatusRunning})
+	if err != nil {
+		h.Error(w, http.StatusInternalServerError, "Failed to list active sessions")
+		return
+	}
+	h.JSON(w, http.StatusOK, ActiveTasksResponse{Count: len(sessions)})
+}
+
 // GetSystemStatus checks system requirements and returns status (including startup tasks)
 func (h *Handler) GetSystemStatus(w http.ResponseWriter, _ *http.Request) {
 	// Use system manager to get complete system status
@@ -42,13 +70,16 @@ func (h *Handler) GetSystemStatus(w http.ResponseWriter, _ *http.Request) {
 
 // SupportInfoResponse contains diagnostic information for debugging and support
 type SupportInfoResponse struct {
-	Version    string                       `json:"version"`
-	Runtime    RuntimeInfo                  `json:"runtime"`
-	Config     ConfigInfo                   `json:"config"`
-	ServerLog  string                       `json:"server_log"`
-	LogPath    string                       `json:"log_path"`
-	LogExists  bool                         `json:"log_exists"`
-	SystemInfo startup.SystemStatusResponse `json:"system_info"`
+	Version        string                       `json:"version"`
+	Runtime        RuntimeInfo                  `json:"runtime"`
+	Config         ConfigInfo                   `json:"config"`
+	ServerLog      string                       `json:"server_log"`
+	LogPath        string                       `json:"log_path"`
+	LogExists      bool                         `json:"log_exists"`
+	ShellLog       string                       `json:"shell_log"`
+	ShellLogPath   string                       `json:"shell_log_path"`
+	ShellLogExists bool                         `json:"shell_log_exists"`
+	SystemInfo     startup.SystemStatusResponse `json:"system_info"`
 }
 
 // RuntimeInfo contains Go runtime information
@@ -158,15 +189,26 @@ func (h *Handler) GetSupportInfo(w http.ResponseWriter, _ *http.Request) {
 		configInfo.VZ = vzInfo
 	}
 
-	// Read server log file (Tauri sidecar log)
-	logPath := filepath.Join(xdg.StateHome, "discobot", "logs", "server.log")
-	logContent := ""
-	logExists := false
-
-	if logData, err := os.ReadFile(logPath); err == nil {
-		logContent = string(logData)
-		logExists = true
+	// Read the current run's server log file (Tauri sidecar log) and the
+	// Tauri app's own log
+	logPath := filepath.Join(h.cfg.LogDir, "server-latest.log")
+	logContent, logExists := readLogFile(logPath)
+	if !logExists {
+		// With split streams the run has stderr and stdout files instead
+		for _, name := range []string{"server-latest.err.log", "server-latest.out.log"} {
+			path := filepath.Join(h.cfg.LogDir, name)
+			content, ok := readLogFile(path)
+			if !ok {
+				continue
+			}
+			if !logExists {
+				logPath, logExists = path, true
+			}
+			logContent += "==> " + name + " <==\n" + content
+		}
 	}
+	shellLogPath := filepath.Join(h.cfg.LogDir, "shell.log")
+	shellLogContent, shellLogExists := readLogFile(shellLogPath)
 
 	// Get system status from system manager
 	var systemStatus startup.SystemStatusResponse
@@ -175,18 +217,30 @@ func (h *Handler) GetSupportInfo(w http.ResponseWriter, _ *http.Request) {
 	}
 
 	response := SupportInfoResponse{
-		Version:    version.Get(),
-		Runtime:    runtimeInfo,
-		Config:     configInfo,
-		ServerLog:  logContent,
-		LogPath:    logPath,
-		LogExists:  logExists,
-		SystemInfo: systemStatus,
+		Version:        version.Get(),
+		Runtime:        runtimeInfo,
+		Config:         configInfo,
+		ServerLog:      logContent,
+		LogPath:        logPath,
+		LogExists:      logExists,
+		ShellLog:       shellLogContent,
+		ShellLogPath:   shellLogPath,
+		ShellLogExists: shellLogExists,
+		SystemInfo:     systemStatus,
 	}
 
 	h.JSON(w, http.StatusOK, response)
 }
 
+// readLogFile returns a log file's content and whether it exists
+func readLogFile(path string) (string, bool) {
+	data, err := os.ReadFile(path)
+	if err != nil {
+		return "", false
+	}
+	return string(data), true
+}
+
 // getDiskUsage returns filesystem usage statistics for a given path
 // Platform-specific implementations in status_unix.go and status_windows.go
 
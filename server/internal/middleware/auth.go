@@ -4,6 +4,7 @@ import (
 	"context"
 	"crypto/subtle"
 	"net/http"
+	"strings"
 
 	"github.com/obot-platform/discobot/server/internal/config"
 	"github.com/obot-platform/discobot/server/internal/model"
@@ -25,7 +26,8 @@ const tauriSecretCookieName = "discobot_secret"
 // TauriAuth middleware validates the Tauri secret from cookie or query string.
 // Only active when cfg.TauriMode is true.
 // Rejects requests without valid secret with 401 Unauthorized.
-// Checks both cookie and ?token= query parameter for flexibility with WebSocket/SSE.
+// Checks the ?token= query parameter (for WebSocket/SSE), an Authorization
+// bearer token (set by the desktop app's discobot-api:// proxy) and the cookie.
 func TauriAuth(cfg *config.Config) func(http.Handler) http.Handler {
 	return func(next http.Handler) http.Handler {
 		return http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
@@ -40,6 +42,8 @@ func TauriAuth(cfg *config.Config) func(http.Handler) http.Handler {
 			// First check query parameter (for WebSocket/SSE URLs)
 			if token := r.URL.Query().Get("token"); token != "" {
 				secret = token
+			} else if token, ok := strings.CutPrefix(r.Header.Get("Authorization"), "Bearer "); ok {
+				secret = token
 			} else if cookie, err := r.Cookie(tauriSecretCookieName); err == nil {
 				// Fall back to cookie
 				secret = cookie.Value
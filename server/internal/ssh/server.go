@@ -37,6 +37,9 @@ type Config struct {
 	// Address to listen on (e.g., ":2222")
 	Address string
 
+	// Listener, if set, is used instead of listening on Address.
+	Listener net.Listener
+
 	// HostKeyPath is the path to the SSH host key file.
 	// If the file doesn't exist, a new key will be generated.
 	HostKeyPath string
@@ -94,22 +97,30 @@ func New(cfg *Config) (*Server, error) {
 		provider:        cfg.SandboxProvider,
 		userInfoFetcher: cfg.UserInfoFetcher,
 		addr:            cfg.Address,
+		listener:        cfg.Listener,
 		sessions:        make(map[string]*sessionHandler),
 	}, nil
 }
 
 // Start begins accepting SSH connections.
 func (s *Server) Start() error {
-	listener, err := net.Listen("tcp", s.addr)
-	if err != nil {
-		return fmt.Errorf("failed to listen on %s: %w", s.addr, err)
-	}
-
 	s.mu.Lock()
-	s.listener = listener
+	listener := s.listener
 	s.mu.Unlock()
 
-	log.Printf("SSH server listening on %s", s.addr)
+	if listener == nil {
+		var err error
+		listener, err = net.Listen("tcp", s.addr)
+		if err != nil {
+			return fmt.Errorf("failed to listen on %s: %w", s.addr, err)
+		}
+
+		s.mu.Lock()
+		s.listener = listener
+		s.mu.Unlock()
+	}
+
+	log.Printf("SSH server listening on %s", listener.Addr())
 
 	for {
 		conn, err := listener.Accept()
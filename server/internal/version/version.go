@@ -1,5 +1,7 @@
 package version
 
+import "runtime/debug"
+
 // Version is the version of the server binary.
 // It is set at build time via -ldflags.
 // Default value is "main" for development builds.
@@ -9,3 +11,18 @@ var Version = "main"
 func Get() string {
 	return Version
 }
+
+// Commit returns the VCS revision the binary was built from, or "" if the
+// build didn't record one.
+func Commit() string {
+	info, ok := debug.ReadBuildInfo()
+	if !ok {
+		return ""
+	}
+	for _, setting := range info.Settings {
+		if setting.Key == "vcs.revision" {
+			return setting.Value
+		}
+	}
+	return ""
+}
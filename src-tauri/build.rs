@@ -1,3 +1,82 @@
+/// App commands, each of which gets `allow-*` and `deny-*` permissions.
+/// Declaring them turns on capability checks for app commands, so a command
+/// must also be granted in `capabilities/` before the frontend can call it.
+const COMMANDS: &[&str] = &[
+    "get_server_port",
+    "get_server_port_info",
+    "get_server_url",
+    "get_ssh_port",
+    "get_ssh_command",
+    "reset_window_state",
+    "get_server_socket",
+    "get_api_proxy_url",
+    "is_server_running",
+    "get_server_secret",
+    "rotate_secret",
+    "secret_store_set",
+    "secret_store_get",
+    "secret_store_delete",
+    "secret_store_list",
+    "get_server_status",
+    "get_server_error",
+    "get_server_info",
+    "get_versions",
+    "is_app_ready",
+    "get_server_bind_info",
+    "restart_server",
+    "start_server_cmd",
+    "stop_server_cmd",
+    "set_server_log_forwarding",
+    "send_server_command",
+    "server_control",
+    "set_server_log_level",
+    "get_server_log_level",
+    "is_window_visible",
+    "show_main_window",
+    "hide_main_window",
+    "quit_app",
+    "save_file_to_downloads",
+    "get_settings",
+    "update_settings",
+    "clear_preferred_port",
+    "set_fixed_port",
+    "get_effective_server_env",
+    "get_server_env",
+    "clear_server_log",
+    "truncate_logs_now",
+    "get_startup_metrics",
+    "get_log_config",
+    "get_server_logs",
+    "list_log_runs",
+    "open_data_dir",
+    "open_config_dir",
+    "tail_server_logs",
+    "untail_server_logs",
+    "open_log_folder",
+    "set_tray_visible",
+    "get_tray_visible",
+    "set_log_file_level",
+    "get_log_file_level",
+    "is_dev_build",
+    "cleanup_orphans",
+    "get_system_locale",
+    "is_first_run",
+    "get_feature_flags",
+    "set_feature_flag",
+    "get_host_resources",
+    "get_vm_limits",
+    "set_vm_limits",
+];
+
 fn main() {
-    tauri_build::build()
+    // The triple the sidecar was built for, for "binary missing" reports
+    println!(
+        "cargo:rustc-env=DISCOBOT_TARGET_TRIPLE={}",
+        std::env::var("TARGET").unwrap()
+    );
+    tauri_build::try_build(
+        tauri_build::Attributes::new()
+            .app_manifest(tauri_build::AppManifest::new().commands(COMMANDS)),
+    )
+    .expect("failed to run tauri-build")
 }
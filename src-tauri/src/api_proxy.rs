@@ -0,0 +1,150 @@
+use std::sync::Mutex;
+
+use log::warn;
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::{Manager, UriSchemeContext, UriSchemeResponder};
+
+use crate::health::Endpoint;
+use crate::secret::Secret;
+use crate::{idle, ServerState, ServerStatus};
+
+/// Scheme the webview uses to reach the server without knowing its secret,
+/// e.g. `discobot-api://localhost/api/sessions`.
+pub const SCHEME: &str = "discobot-api";
+
+/// Request headers not forwarded: the client sets its own transport headers,
+/// and the webview doesn't get to choose the credentials.
+const SKIPPED_HEADERS: [header::HeaderName; 6] = [
+    header::HOST,
+    header::CONTENT_LENGTH,
+    header::CONNECTION,
+    header::TRANSFER_ENCODING,
+    header::AUTHORIZATION,
+    header::COOKIE,
+];
+
+/// Base URL of the scheme. Windows webviews only load custom schemes as
+/// `http://<scheme>.localhost`.
+pub fn base_url() -> String {
+    if cfg!(windows) {
+        format!("http://{}.localhost", SCHEME)
+    } else {
+        format!("{}://localhost", SCHEME)
+    }
+}
+
+/// Base URL for API requests that the app authenticates on the frontend's
+/// behalf.
+#[tauri::command]
+pub fn get_api_proxy_url() -> String {
+    base_url()
+}
+
+/// Handle a `discobot-api://` request from the webview by forwarding it to the
+/// server, over TCP or its socket, with the secret attached. Tauri hands over
+/// and takes back whole bodies, so streaming endpoints (SSE, WebSockets) only
+/// work over the server's own URL. Like `get_server_secret`, it only serves
+/// the main window. A server suspended while idle is resumed first.
+pub fn handle_request(
+    ctx: UriSchemeContext<'_, tauri::Wry>,
+    request: Request<Vec<u8>>,
+    responder: UriSchemeResponder,
+) {
+    if ctx.webview_label() != "main" {
+        warn!(
+            "Refused an API request from window \"{}\"",
+            ctx.webview_label()
+        );
+        let message = "The API is only available to the main window";
+        responder.respond(error_response(StatusCode::FORBIDDEN, message));
+        return;
+    }
+    let app = ctx.app_handle().clone();
+    tauri::async_runtime::spawn(async move {
+        // A server suspended while idle is started again for the request
+        idle::wake(&app).await;
+        let target = {
+            let state = app.state::<Mutex<ServerState>>();
+            let state = state.lock().unwrap();
+            match state.status {
+                ServerStatus::Running { .. } | ServerStatus::External => {
+                    Some((state.endpoint(), state.secret.clone()))
+                }
+                _ => None,
+            }
+        };
+        let Some((endpoint, secret)) = target else {
+            let message = "The server isn't running";
+            responder.respond(error_response(StatusCode::SERVICE_UNAVAILABLE, message));
+            return;
+        };
+        let response = forward(&endpoint, &secret, request)
+            .await
+            .unwrap_or_else(|e| {
+                warn!("{}", e);
+                error_response(StatusCode::BAD_GATEWAY, &e)
+            });
+        responder.respond(response);
+    });
+}
+
+fn client(endpoint: &Endpoint) -> Result<reqwest::Client, String> {
+    let builder = reqwest::Client::builder().no_proxy();
+    #[cfg(unix)]
+    let builder = match &endpoint.socket {
+        Some(socket) => builder.unix_socket(socket.clone()),
+        None => builder,
+    };
+    builder
+        .build()
+        .map_err(|e| format!("Failed to create server client: {}", e))
+}
+
+async fn forward(
+    endpoint: &Endpoint,
+    secret: &Secret,
+    request: Request<Vec<u8>>,
+) -> Result<Response<Vec<u8>>, String> {
+    let path = request
+        .uri()
+        .path_and_query()
+        .map_or("/", |path| path.as_str());
+    let mut forwarded = client(endpoint)?.request(request.method().clone(), endpoint.url(path));
+    for (name, value) in request.headers() {
+        if !SKIPPED_HEADERS.contains(name) {
+            forwarded = forwarded.header(name, value);
+        }
+    }
+    let response = forwarded
+        .bearer_auth(secret.expose())
+        .body(request.into_body())
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach the server: {}", e))?;
+
+    let mut builder = Response::builder().status(response.status());
+    for (name, value) in response.headers() {
+        if !SKIPPED_HEADERS.contains(name) {
+            builder = builder.header(name, value);
+        }
+    }
+    let body = response
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to read server response: {}", e))?;
+    builder
+        .body(body.to_vec())
+        .map_err(|e| format!("Failed to build response: {}", e))
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response<Vec<u8>> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(
+            serde_json::json!({ "error": message })
+                .to_string()
+                .into_bytes(),
+        )
+        .unwrap_or_default()
+}
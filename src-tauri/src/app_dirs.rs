@@ -0,0 +1,32 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use tauri_plugin_opener::OpenerExt;
+
+/// The app's folder within a platform directory, created if absent.
+fn app_dir(base: Option<PathBuf>, kind: &str) -> Result<PathBuf, String> {
+    let dir = base
+        .ok_or_else(|| format!("Could not determine {} directory", kind))?
+        .join("discobot");
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {} directory: {}", kind, e))?;
+    Ok(dir)
+}
+
+fn open_dir(app: &tauri::AppHandle, dir: &Path) -> Result<(), String> {
+    app.opener()
+        .open_path(dir.display().to_string(), None::<&str>)
+        .map_err(|e| format!("Failed to open {}: {}", dir.display(), e))
+}
+
+/// Open the data directory (first-run marker, fallback secrets) in the file
+/// manager, e.g. when support asks for its contents.
+#[tauri::command]
+pub fn open_data_dir(app: tauri::AppHandle) -> Result<(), String> {
+    open_dir(&app, &app_dir(dirs::data_dir(), "data")?)
+}
+
+/// Open the config directory (settings.json, server.env) in the file manager.
+#[tauri::command]
+pub fn open_config_dir(app: tauri::AppHandle) -> Result<(), String> {
+    open_dir(&app, &app_dir(dirs::config_dir(), "config")?)
+}
@@ -0,0 +1,49 @@
+use std::sync::Mutex;
+
+use tauri::{Emitter, Manager};
+
+/// Emitted once, when the server first reports ready and the main window has
+/// been shown, whichever happens last.
+const APP_READY_EVENT: &str = "app-ready";
+
+/// Tracks the two halves of startup so `app-ready` fires exactly once.
+#[derive(Default)]
+pub struct AppReady(Mutex<Progress>);
+
+#[derive(Default)]
+struct Progress {
+    server_ready: bool,
+    window_shown: bool,
+    emitted: bool,
+}
+
+/// Note that the server is up. Later restarts don't emit `app-ready` again.
+pub fn mark_server_ready(app: &tauri::AppHandle) {
+    update(app, |progress| progress.server_ready = true);
+}
+
+/// Note that the main window has been shown.
+pub fn mark_window_shown(app: &tauri::AppHandle) {
+    update(app, |progress| progress.window_shown = true);
+}
+
+fn update(app: &tauri::AppHandle, change: impl FnOnce(&mut Progress)) {
+    let ready = {
+        let state = app.state::<AppReady>();
+        let mut progress = state.0.lock().unwrap();
+        change(&mut progress);
+        let ready = progress.server_ready && progress.window_shown && !progress.emitted;
+        progress.emitted |= ready;
+        ready
+    };
+    if ready {
+        let _ = app.emit(APP_READY_EVENT, ());
+    }
+}
+
+/// Whether `app-ready` has been emitted, for a frontend that starts listening
+/// after the fact.
+#[tauri::command]
+pub fn is_app_ready(state: tauri::State<'_, AppReady>) -> bool {
+    state.0.lock().unwrap().emitted
+}
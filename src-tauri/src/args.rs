@@ -0,0 +1,117 @@
+use std::path::Path;
+
+use serde::Serialize;
+
+/// Deep links are routed to the deep-link plugin rather than reported here.
+const URL_SCHEME: &str = "discobot://";
+
+/// Run the server without ever creating the main window, e.g. on a machine
+/// that only hosts the backend. There is no tray either; Ctrl-C (SIGINT)
+/// stops the server gracefully and quits.
+///
+/// Server lifecycle management (crash restarts, wake and network recovery)
+/// works as usual, and the server can be used from a browser or other
+/// clients via its port and secret. Window, tray and close-behavior commands
+/// are no-ops, and the server is never suspended for idleness.
+pub const HEADLESS_FLAG: &str = "--headless";
+
+/// Start with the window hidden and the app in the tray, like
+/// `Settings::start_hidden`. Meant for launches at login.
+pub const HIDDEN_FLAG: &str = "--hidden";
+
+/// Emitted when a second launch forwards arguments to the running instance.
+pub const SECOND_INSTANCE_EVENT: &str = "second-instance-args";
+
+/// Something the user asked discobot to open from the command line.
+///
+/// Serialized as `{ "kind": "path", "value": "/absolute/path" }`.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "kind", content = "value", rename_all = "lowercase")]
+pub enum OpenTarget {
+    Path(String),
+}
+
+/// Payload of `second-instance-args`: the recognized targets in the order
+/// they were given, plus the working directory of the second launch.
+#[derive(Clone, Debug, Serialize)]
+pub struct SecondInstanceArgs {
+    pub targets: Vec<OpenTarget>,
+    pub cwd: String,
+}
+
+/// Whether this launch asked for `--headless`.
+pub fn is_headless(args: &[String]) -> bool {
+    args.iter().skip(1).any(|arg| arg == HEADLESS_FLAG)
+}
+
+/// Whether this launch asked for `--hidden`.
+pub fn is_hidden(args: &[String]) -> bool {
+    args.iter().skip(1).any(|arg| arg == HIDDEN_FLAG)
+}
+
+/// Extract open targets from a launch's arguments.
+///
+/// The first argument is the executable and is skipped. After that:
+/// - `discobot://...` is skipped; the deep-link plugin emits `open-deep-link`,
+/// - anything else not starting with `-` is a path, resolved against `cwd`,
+///   and kept only if it exists,
+/// - flags (`-x`, `--foo`) are ignored.
+pub fn parse_open_targets(args: &[String], cwd: &str) -> Vec<OpenTarget> {
+    args.iter()
+        .skip(1)
+        .filter_map(|arg| {
+            if arg.starts_with(URL_SCHEME) || arg.starts_with('-') {
+                return None;
+            }
+            let path = Path::new(cwd).join(arg);
+            path.exists()
+                .then(|| OpenTarget::Path(path.to_string_lossy().to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn flags_after_the_executable() {
+        assert!(is_headless(&args(&["discobot", "--headless"])));
+        assert!(is_hidden(&args(&["discobot", "x", "--hidden"])));
+        assert!(!is_headless(&args(&["--headless"])));
+        assert!(!is_hidden(&args(&["discobot", "--headless"])));
+    }
+
+    #[test]
+    fn open_targets_are_existing_paths() {
+        let cwd = std::env::temp_dir().join(format!("discobot-args-test-{}", std::process::id()));
+        std::fs::create_dir_all(cwd.join("project")).unwrap();
+        let cwd_str = cwd.to_string_lossy().to_string();
+
+        let targets = parse_open_targets(
+            &args(&[
+                "project",
+                "project",
+                "--hidden",
+                "-v",
+                "discobot://open",
+                "missing",
+            ]),
+            &cwd_str,
+        );
+        let paths: Vec<String> = targets
+            .into_iter()
+            .map(|OpenTarget::Path(path)| path)
+            .collect();
+        assert_eq!(
+            paths,
+            vec![cwd.join("project").to_string_lossy().to_string()]
+        );
+
+        let _ = std::fs::remove_dir_all(&cwd);
+    }
+}
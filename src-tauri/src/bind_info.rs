@@ -0,0 +1,50 @@
+use std::net::SocketAddr;
+use std::sync::Mutex;
+
+use log::warn;
+use serde::Serialize;
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+
+use crate::ServerState;
+
+/// Where the server is listening, for the diagnostics panel.
+#[derive(Serialize)]
+pub struct BindInfo {
+    /// As reported by the sidecar, e.g. `127.0.0.1:4000`. `None` until it
+    /// reports it, for external servers, and in socket mode.
+    address: Option<String>,
+    socket: Option<String>,
+    /// Whether the server is known to be reachable from this machine only.
+    loopback_only: bool,
+}
+
+/// The server relies on being reachable from this machine only, so warn
+/// prominently if it's listening on any other interface.
+pub fn check(app: &tauri::AppHandle, addr: SocketAddr) {
+    if addr.ip().is_loopback() {
+        return;
+    }
+    let message = format!(
+        "The Discobot server is listening on {}, so other devices on your \
+         network may be able to reach it. It should only listen on localhost; \
+         please report this as a bug.",
+        addr
+    );
+    warn!("{}", message);
+    app.dialog()
+        .message(message)
+        .title("Discobot server exposed")
+        .kind(MessageDialogKind::Warning)
+        .show(|_| {});
+}
+
+#[tauri::command]
+pub fn get_server_bind_info(state: tauri::State<'_, Mutex<ServerState>>) -> BindInfo {
+    let state = state.lock().unwrap();
+    BindInfo {
+        address: state.bind_addr.map(|addr| addr.to_string()),
+        socket: state.socket.as_ref().map(|path| path.display().to_string()),
+        loopback_only: state.socket.is_some()
+            || state.bind_addr.is_some_and(|addr| addr.ip().is_loopback()),
+    }
+}
@@ -0,0 +1,178 @@
+use log::warn;
+use tauri::Url;
+
+use crate::settings::Settings;
+
+/// Where the webview loads from; always allowed.
+const TAURI_ORIGINS: [&str; 2] = ["http://tauri.localhost", "tauri://localhost"];
+/// The frontend dev server, which serves the webview in dev builds.
+const DEV_ORIGIN: &str = "http://localhost:3000";
+
+/// Check that `origin` is a bare origin such as `http://localhost:5173`,
+/// returning it normalized. Wildcards (`*` or `https://*.example.com`) are
+/// only accepted with `allow_wildcard`, since they open the API to any
+/// matching site.
+pub fn validate_origin(origin: &str, allow_wildcard: bool) -> Result<String, String> {
+    let origin = origin.trim();
+    if origin.contains('*') {
+        if !allow_wildcard {
+            return Err(format!(
+                "CORS origin '{}' contains a wildcard, which must be allowed explicitly",
+                origin
+            ));
+        }
+        // The URL parser can't judge patterns, so only check the shape
+        if origin == "*"
+            || origin
+                .split_once("://")
+                .is_some_and(|(_, host)| !host.contains('/'))
+        {
+            return Ok(origin.to_string());
+        }
+        return Err(format!("Invalid CORS origin '{}'", origin));
+    }
+
+    let url = Url::parse(origin).map_err(|e| format!("Invalid CORS origin '{}': {}", origin, e))?;
+    if url.host_str().is_none_or(str::is_empty) {
+        return Err(format!("CORS origin '{}' has no host", origin));
+    }
+    if !url.username().is_empty()
+        || url.password().is_some()
+        || !matches!(url.path(), "" | "/")
+        || url.query().is_some()
+        || url.fragment().is_some()
+    {
+        return Err(format!(
+            "CORS origin '{}' must only have a scheme, host and port",
+            origin
+        ));
+    }
+    // Drops the trailing slash the parser adds and any default port
+    let port = url
+        .port()
+        .map(|port| format!(":{}", port))
+        .unwrap_or_default();
+    Ok(format!(
+        "{}://{}{}",
+        url.scheme(),
+        url.host_str().unwrap_or_default(),
+        port
+    ))
+}
+
+/// Check every extra origin in `settings`.
+pub fn validate(settings: &Settings) -> Result<(), String> {
+    for origin in &settings.extra_cors_origins {
+        validate_origin(origin, settings.allow_wildcard_cors)?;
+    }
+    Ok(())
+}
+
+/// `CORS_ORIGINS` for the sidecar: the webview's origins plus the valid extra
+/// ones from settings. Invalid entries, e.g. from a hand-edited settings
+/// file, are skipped with a warning.
+pub fn origins(settings: &Settings) -> String {
+    let mut origins: Vec<String> = TAURI_ORIGINS
+        .iter()
+        .map(|origin| origin.to_string())
+        .collect();
+    if cfg!(debug_assertions) {
+        origins.insert(0, DEV_ORIGIN.to_string());
+    }
+    for origin in &settings.extra_cors_origins {
+        match validate_origin(origin, settings.allow_wildcard_cors) {
+            Ok(origin) if !origins.contains(&origin) => origins.push(origin),
+            Ok(_) => {}
+            Err(e) => warn!("Ignoring extra CORS origin: {}", e),
+        }
+    }
+    origins.join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(extra: &[&str], allow_wildcard: bool) -> Settings {
+        Settings {
+            extra_cors_origins: extra.iter().map(|origin| origin.to_string()).collect(),
+            allow_wildcard_cors: allow_wildcard,
+            ..Settings::default()
+        }
+    }
+
+    #[test]
+    fn normalizes_origins() {
+        assert_eq!(
+            validate_origin(" http://localhost:5173/ ", false).unwrap(),
+            "http://localhost:5173"
+        );
+        assert_eq!(
+            validate_origin("https://example.com:443", false).unwrap(),
+            "https://example.com"
+        );
+    }
+
+    #[test]
+    fn rejects_more_than_an_origin() {
+        for origin in [
+            "localhost:5173",
+            "http://",
+            "http://user@example.com",
+            "http://example.com/app",
+            "http://example.com?x=1",
+            "http://example.com#top",
+        ] {
+            assert!(validate_origin(origin, false).is_err(), "{}", origin);
+        }
+    }
+
+    #[test]
+    fn wildcards_must_be_allowed() {
+        assert!(validate_origin("*", false).is_err());
+        assert!(validate_origin("https://*.example.com", false).is_err());
+        assert_eq!(validate_origin("*", true).unwrap(), "*");
+        assert_eq!(
+            validate_origin("https://*.example.com", true).unwrap(),
+            "https://*.example.com"
+        );
+        assert!(validate_origin("https://*.example.com/app", true).is_err());
+    }
+
+    #[test]
+    fn validate_checks_every_extra_origin() {
+        assert!(validate(&settings(&["http://localhost:5173"], false)).is_ok());
+        assert!(validate(&settings(&["http://localhost:5173", "*"], false)).is_err());
+        assert!(validate(&settings(&["http://localhost:5173", "*"], true)).is_ok());
+    }
+
+    #[test]
+    fn origins_skip_invalid_and_duplicate_entries() {
+        let origins = origins(&settings(
+            &[
+                "http://localhost:5173/",
+                "http://localhost:5173",
+                "not an origin",
+                "tauri://localhost",
+            ],
+            false,
+        ));
+        let origins: Vec<&str> = origins.split(',').collect();
+        assert_eq!(
+            origins
+                .iter()
+                .filter(|o| **o == "http://localhost:5173")
+                .count(),
+            1
+        );
+        assert_eq!(
+            origins
+                .iter()
+                .filter(|o| **o == "tauri://localhost")
+                .count(),
+            1
+        );
+        assert!(origins.contains(&"http://tauri.localhost"));
+        assert!(!origins.contains(&"not an origin"));
+    }
+}
@@ -0,0 +1,115 @@
+use std::cell::OnceCell;
+use std::sync::OnceLock;
+
+use log::warn;
+use objc2::rc::Retained;
+use objc2::runtime::{AnyObject, Imp, Sel};
+use objc2::{define_class, msg_send, sel, MainThreadMarker, MainThreadOnly};
+use objc2_app_kit::{NSApplication, NSMenu, NSMenuItem};
+use objc2_foundation::{NSObject, NSString};
+
+/// Handle used by the menu actions, which AppKit calls without any context.
+static APP: OnceLock<tauri::AppHandle> = OnceLock::new();
+
+thread_local! {
+    /// The Dock menu and the target its items send actions to. Menu items only
+    /// hold a weak reference to their target, so it's kept alive here.
+    static DOCK_MENU: OnceCell<(Retained<NSMenu>, Retained<DockMenuTarget>)> =
+        const { OnceCell::new() };
+}
+
+define_class!(
+    // SAFETY: NSObject has no subclassing requirements and this class
+    // doesn't implement Drop.
+    #[unsafe(super(NSObject))]
+    #[thread_kind = MainThreadOnly]
+    #[name = "DiscobotDockMenuTarget"]
+    struct DockMenuTarget;
+
+    impl DockMenuTarget {
+        #[unsafe(method(show:))]
+        fn show(&self, _sender: Option<&AnyObject>) {
+            if let Some(app) = APP.get() {
+                crate::show_window(app);
+            }
+        }
+
+        #[unsafe(method(quit:))]
+        fn quit(&self, _sender: Option<&AnyObject>) {
+            if let Some(app) = APP.get() {
+                crate::quit::request_quit(app);
+            }
+        }
+    }
+);
+
+/// `-[NSApplicationDelegate applicationDockMenu:]`, added to the app delegate
+/// since neither Tauri nor tao expose a Dock menu.
+unsafe extern "C-unwind" fn application_dock_menu(
+    _this: &AnyObject,
+    _cmd: Sel,
+    _sender: &AnyObject,
+) -> *mut NSMenu {
+    DOCK_MENU.with(|dock_menu| {
+        dock_menu.get().map_or(std::ptr::null_mut(), |(menu, _)| {
+            Retained::as_ptr(menu) as *mut NSMenu
+        })
+    })
+}
+
+/// Give the Dock icon the same Show/Quit items as the tray menu. Safe to call
+/// repeatedly; the menu is only built once.
+pub fn install_menu(app: &tauri::AppHandle) {
+    let handle = app.clone();
+    let _ = app.run_on_main_thread(move || {
+        let Some(mtm) = MainThreadMarker::new() else {
+            return;
+        };
+        if DOCK_MENU.with(|dock_menu| dock_menu.get().is_some()) {
+            return;
+        }
+        let _ = APP.set(handle);
+
+        let ns_app = NSApplication::sharedApplication(mtm);
+        let delegate: Option<Retained<AnyObject>> = unsafe { msg_send![&ns_app, delegate] };
+        let Some(delegate) = delegate else {
+            warn!("No application delegate, skipping Dock menu");
+            return;
+        };
+
+        let target: Retained<DockMenuTarget> =
+            unsafe { msg_send![DockMenuTarget::alloc(mtm), init] };
+        let menu = NSMenu::new(mtm);
+        for (title, action) in [("Show", sel!(show:)), ("Quit", sel!(quit:))] {
+            let item = unsafe {
+                NSMenuItem::initWithTitle_action_keyEquivalent(
+                    NSMenuItem::alloc(mtm),
+                    &NSString::from_str(title),
+                    Some(action),
+                    &NSString::from_str(""),
+                )
+            };
+            unsafe { item.setTarget(Some(&target)) };
+            menu.addItem(&item);
+        }
+
+        // SAFETY: the signature matches `applicationDockMenu:` ("@@:@"), and
+        // adding a method is a no-op if the delegate already implements it.
+        unsafe {
+            let imp: Imp = std::mem::transmute(
+                application_dock_menu
+                    as unsafe extern "C-unwind" fn(&AnyObject, Sel, &AnyObject) -> *mut NSMenu,
+            );
+            objc2::ffi::class_addMethod(
+                delegate.class() as *const _ as *mut _,
+                sel!(applicationDockMenu:),
+                imp,
+                c"@@:@".as_ptr(),
+            );
+        }
+
+        DOCK_MENU.with(|dock_menu| {
+            let _ = dock_menu.set((menu, target));
+        });
+    });
+}
@@ -0,0 +1,54 @@
+/// `host:port` of a server to attach to instead of spawning the sidecar.
+const EXTERNAL_SERVER_ENV: &str = "DISCOBOT_EXTERNAL_SERVER";
+
+/// Secret the external server was started with; required in external mode.
+const SECRET_ENV: &str = "DISCOBOT_SECRET";
+
+/// A server started outside the app, e.g. under a debugger.
+pub struct ExternalServer {
+    pub host: String,
+    pub port: u16,
+    pub secret: String,
+}
+
+/// Determine whether to attach to an external server. The environment
+/// variable takes precedence over `setting`; neither being set means the
+/// sidecar should be spawned as usual.
+pub fn resolve(setting: Option<&str>) -> Result<Option<ExternalServer>, String> {
+    let address = match std::env::var(EXTERNAL_SERVER_ENV) {
+        Ok(value) if !value.is_empty() => value,
+        _ => match setting {
+            Some(value) if !value.is_empty() => value.to_string(),
+            _ => return Ok(None),
+        },
+    };
+
+    let (host, port) = address
+        .rsplit_once(':')
+        .filter(|(host, _)| !host.is_empty())
+        .ok_or_else(|| {
+            format!(
+                "Invalid external server address '{}', expected host:port",
+                address
+            )
+        })?;
+    let port = port
+        .parse()
+        .map_err(|_| format!("Invalid port in external server address '{}'", address))?;
+
+    let secret = std::env::var(SECRET_ENV)
+        .ok()
+        .filter(|secret| !secret.is_empty())
+        .ok_or_else(|| {
+            format!(
+                "{} must be set when attaching to an external server",
+                SECRET_ENV
+            )
+        })?;
+
+    Ok(Some(ExternalServer {
+        host: host.to_string(),
+        port,
+        secret,
+    }))
+}
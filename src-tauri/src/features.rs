@@ -0,0 +1,59 @@
+use std::sync::Mutex;
+
+use tauri::Manager;
+
+use crate::settings::{self, FeatureFlags, Settings};
+
+impl FeatureFlags {
+    /// The sidecar environment variables controlled by these flags.
+    pub fn env(&self) -> Vec<(&'static str, String)> {
+        [
+            ("SUGGESTIONS_ENABLED", self.suggestions),
+            ("SSH_ENABLED", self.ssh),
+            ("LOCAL_PROVIDER_ENABLED", self.local_provider),
+            ("DEBUG_DOCKER", self.debug_docker),
+        ]
+        .into_iter()
+        .map(|(key, enabled)| (key, enabled.to_string()))
+        .collect()
+    }
+
+    fn flag_mut(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "suggestions" => Some(&mut self.suggestions),
+            "ssh" => Some(&mut self.ssh),
+            "local_provider" => Some(&mut self.local_provider),
+            "debug_docker" => Some(&mut self.debug_docker),
+            _ => None,
+        }
+    }
+}
+
+#[tauri::command]
+pub fn get_feature_flags(state: tauri::State<'_, Mutex<Settings>>) -> FeatureFlags {
+    state.lock().unwrap().feature_flags.clone()
+}
+
+/// Turn a server feature on or off. Flags are read when the server starts, so
+/// a change marks the running server as needing a restart.
+#[tauri::command]
+pub fn set_feature_flag(app: tauri::AppHandle, name: String, enabled: bool) -> Result<(), String> {
+    {
+        let state = app.state::<Mutex<Settings>>();
+        let mut current = state.lock().unwrap();
+        let mut updated = current.clone();
+        let flag = updated
+            .feature_flags
+            .flag_mut(&name)
+            .ok_or_else(|| format!("Unknown feature flag '{}'", name))?;
+        if *flag == enabled {
+            return Ok(());
+        }
+        *flag = enabled;
+        settings::save(&updated)?;
+        *current = updated;
+    }
+
+    crate::mark_restart_required(&app);
+    Ok(())
+}
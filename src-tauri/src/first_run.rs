@@ -0,0 +1,74 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use log::warn;
+use tauri::webview::{PageLoadEvent, PageLoadPayload};
+use tauri::{Emitter, Webview};
+
+/// Emitted once, when the main window first finishes loading on the first
+/// launch, so the frontend can show onboarding.
+const FIRST_RUN_EVENT: &str = "first-run";
+
+/// Whether this launch is the app's first.
+pub struct FirstRun {
+    first_run: bool,
+    /// Cleared once `first-run` has been emitted so reloads don't repeat it.
+    pending: AtomicBool,
+}
+
+fn get_marker_path() -> Result<PathBuf, String> {
+    let data_dir =
+        dirs::data_dir().ok_or_else(|| "Could not determine data directory".to_string())?;
+    Ok(data_dir.join("discobot").join("first-run"))
+}
+
+impl FirstRun {
+    /// Check for the first-run marker, creating it if it's missing so later
+    /// launches behave normally.
+    pub fn detect() -> Self {
+        let first_run = match get_marker_path() {
+            Ok(path) if !path.exists() => {
+                let created = path
+                    .parent()
+                    .map_or(Ok(()), fs::create_dir_all)
+                    .and_then(|()| fs::write(&path, ""));
+                if let Err(e) = created {
+                    warn!("Failed to write first-run marker: {}", e);
+                }
+                true
+            }
+            Ok(_) => false,
+            Err(e) => {
+                warn!("{}, assuming this isn't the first run", e);
+                false
+            }
+        };
+
+        Self {
+            first_run,
+            pending: AtomicBool::new(first_run),
+        }
+    }
+
+    pub fn is_first_run(&self) -> bool {
+        self.first_run
+    }
+
+    /// Emit `first-run` once the main window's page has loaded and can listen.
+    pub fn on_page_load(&self, webview: &Webview, payload: &PageLoadPayload<'_>) {
+        if webview.label() == "main"
+            && payload.event() == PageLoadEvent::Finished
+            && self.pending.swap(false, Ordering::SeqCst)
+        {
+            let _ = webview.emit(FIRST_RUN_EVENT, ());
+        }
+    }
+}
+
+/// Also available as a command in case the event fired before the frontend
+/// subscribed.
+#[tauri::command]
+pub fn is_first_run(state: tauri::State<'_, FirstRun>) -> bool {
+    state.is_first_run()
+}
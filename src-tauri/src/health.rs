@@ -0,0 +1,136 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+/// How long a single probe may take before the server is considered unresponsive.
+const HEALTH_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Response of the server's `/version` endpoint.
+#[derive(Deserialize)]
+pub struct ServerVersion {
+    pub version: String,
+    pub commit: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ActiveTasks {
+    count: u32,
+}
+
+/// Where the shell reaches the server: `host:port` over TCP, or a Unix socket
+/// (macOS and Linux only).
+#[derive(Clone)]
+pub struct Endpoint {
+    pub host: String,
+    pub port: u16,
+    pub socket: Option<PathBuf>,
+}
+
+impl Endpoint {
+    pub fn tcp(host: &str, port: u16) -> Self {
+        Self {
+            host: host.to_string(),
+            port,
+            socket: None,
+        }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        match self.socket {
+            // The host only fills in the URL; the socket decides where it goes
+            Some(_) => format!("http://localhost{}", path),
+            None => format!("http://{}:{}{}", self.host, self.port, path),
+        }
+    }
+}
+
+fn client(endpoint: &Endpoint) -> Option<reqwest::Client> {
+    let builder = reqwest::Client::builder()
+        .timeout(HEALTH_TIMEOUT)
+        .no_proxy();
+    #[cfg(unix)]
+    let builder = match &endpoint.socket {
+        Some(socket) => builder.unix_socket(socket.clone()),
+        None => builder,
+    };
+    #[cfg(not(unix))]
+    let _ = endpoint;
+    builder.build().ok()
+}
+
+/// Probe the server's unauthenticated `/health` endpoint.
+pub async fn check_health(endpoint: &Endpoint) -> bool {
+    let Some(client) = client(endpoint) else {
+        return false;
+    };
+
+    match client.get(endpoint.url("/health")).send().await {
+        Ok(response) => response.status().is_success(),
+        Err(_) => false,
+    }
+}
+
+/// Fetch the server's version and build commit, or `None` if it can't be
+/// reached.
+pub async fn server_version(endpoint: &Endpoint) -> Option<ServerVersion> {
+    client(endpoint)?
+        .get(endpoint.url("/version"))
+        .send()
+        .await
+        .ok()?
+        .error_for_status()
+        .ok()?
+        .json()
+        .await
+        .ok()
+}
+
+/// Run a maintenance action through the server's `/api/control` endpoint and
+/// return its JSON reply.
+pub async fn control(endpoint: &Endpoint, secret: &str, action: &str) -> Result<String, String> {
+    let client = client(endpoint).ok_or_else(|| "Failed to create server client".to_string())?;
+    let response = client
+        .post(endpoint.url("/api/control"))
+        .bearer_auth(secret)
+        .json(&serde_json::json!({ "action": action }))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach the server: {}", e))?;
+    let status = response.status();
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read server response: {}", e))?;
+    if !status.is_success() {
+        return Err(format!(
+            "Server rejected '{}' ({}): {}",
+            action,
+            status,
+            body.trim()
+        ));
+    }
+    Ok(body)
+}
+
+/// Ask the server how many sessions have work in progress. `None` if the
+/// server can't be reached in time, so callers never hang on a dead server.
+pub async fn active_tasks(endpoint: &Endpoint, secret: &str) -> Option<u32> {
+    let response = client(endpoint)?
+        .get(endpoint.url("/api/active-tasks"))
+        // The cookie keeps the secret out of the server's request log
+        .header(
+            reqwest::header::COOKIE,
+            format!("discobot_secret={}", secret),
+        )
+        .send()
+        .await
+        .ok()?
+        .error_for_status()
+        .ok()?;
+    response
+        .json::<ActiveTasks>()
+        .await
+        .ok()
+        .map(|tasks| tasks.count)
+}
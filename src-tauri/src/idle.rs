@@ -0,0 +1,130 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use log::info;
+use tauri::Manager;
+
+use crate::settings::Settings;
+use crate::{
+    health, kill_sidecar, set_server_status, spawn_server_task, ServerState, ServerStatus,
+};
+
+/// How often the monitor checks whether the app has gone idle.
+const TICK_INTERVAL: Duration = Duration::from_secs(60);
+/// Longest `wake` waits for a resumed server to come up.
+const WAKE_TIMEOUT: Duration = Duration::from_secs(30);
+const WAKE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Stop the sidecar once the main window has been hidden for
+/// `Settings::idle_suspend_minutes` and the server has no agent tasks in
+/// progress. It's started again by `resume` the next time the window is
+/// shown, or by `wake` when an API request or command needs it.
+pub fn spawn_idle_monitor(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut hidden_since: Option<Instant> = None;
+        loop {
+            tokio::time::sleep(TICK_INTERVAL).await;
+
+            if window_visible(&app) {
+                hidden_since = None;
+                continue;
+            }
+            let hidden_for = hidden_since.get_or_insert_with(Instant::now).elapsed();
+            let timeout = app
+                .state::<Mutex<Settings>>()
+                .lock()
+                .unwrap()
+                .idle_suspend_minutes;
+            if let Some(minutes) = timeout {
+                if hidden_for >= Duration::from_secs(u64::from(minutes) * 60) {
+                    suspend_if_idle(&app).await;
+                }
+            }
+        }
+    });
+}
+
+fn window_visible(app: &tauri::AppHandle) -> bool {
+    app.get_webview_window("main")
+        .map(|window| window.is_visible().unwrap_or(false))
+        .unwrap_or(false)
+}
+
+async fn suspend_if_idle(app: &tauri::AppHandle) {
+    let (endpoint, secret) = {
+        let state = app.state::<Mutex<ServerState>>();
+        let state = state.lock().unwrap();
+        if state.shutting_down
+            || state.suspended
+            || state.process.is_none()
+            || !matches!(state.status, ServerStatus::Running { .. })
+        {
+            return;
+        }
+        (state.endpoint(), state.secret.clone())
+    };
+
+    // An unreachable server can't confirm it's idle, so leave it alone
+    if health::active_tasks(&endpoint, secret.expose()).await != Some(0) {
+        return;
+    }
+
+    // The window may have been shown while the server was being asked
+    if window_visible(app) {
+        return;
+    }
+    let process = {
+        let state = app.state::<Mutex<ServerState>>();
+        let mut state = state.lock().unwrap();
+        if state.shutting_down || state.suspended {
+            return;
+        }
+        // Invalidate the supervisor so the kill isn't treated as a crash.
+        state.generation += 1;
+        state.suspended = true;
+        state.env = None;
+        state.process.take()
+    };
+    if let Some(child) = process {
+        let _ = tauri::async_runtime::spawn_blocking(move || kill_sidecar(child)).await;
+    }
+    info!("Server suspended while idle");
+    set_server_status(app, ServerStatus::Suspended);
+}
+
+/// Start a suspended sidecar again on the same port and secret, so the
+/// frontend doesn't need to re-handshake. Does nothing if it isn't suspended.
+pub fn resume(app: &tauri::AppHandle) {
+    {
+        let state = app.state::<Mutex<ServerState>>();
+        let mut state = state.lock().unwrap();
+        if !state.suspended || state.shutting_down {
+            return;
+        }
+        state.suspended = false;
+    }
+    info!("Resuming suspended server");
+    set_server_status(app, ServerStatus::Starting);
+    spawn_server_task(app);
+}
+
+/// Resume a suspended server for something that needs it, e.g. an API
+/// request, and wait up to `WAKE_TIMEOUT` for it to come up. Returns right
+/// away if it isn't suspended.
+pub async fn wake(app: &tauri::AppHandle) {
+    if !app.state::<Mutex<ServerState>>().lock().unwrap().suspended {
+        return;
+    }
+    resume(app);
+    let deadline = Instant::now() + WAKE_TIMEOUT;
+    while Instant::now() < deadline {
+        let starting = matches!(
+            app.state::<Mutex<ServerState>>().lock().unwrap().status,
+            ServerStatus::Starting
+        );
+        if !starting {
+            return;
+        }
+        tokio::time::sleep(WAKE_POLL_INTERVAL).await;
+    }
+}
@@ -0,0 +1,268 @@
+use std::fs::{self, File, TryLockError};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager};
+use tauri_plugin_dialog::{
+    DialogExt, MessageDialogButtons, MessageDialogKind, MessageDialogResult,
+};
+
+use crate::secret::{Redactor, Secret};
+use crate::{
+    app_ready, secret_access, set_server_status, PortChanged, ServerState, ServerStatus,
+    SERVER_PORT_EVENT,
+};
+
+const USE_EXISTING_BUTTON: &str = "Use Running Server";
+const QUIT_BUTTON: &str = "Quit";
+
+/// The instance holding the lock. Kept in `instance.json` next to the lock
+/// file, since Windows won't let other processes read a locked file.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LockInfo {
+    pub pid: u32,
+    pub port: u16,
+    /// 0 when recorded by a version that didn't include it.
+    #[serde(default)]
+    pub ssh_port: u16,
+    pub socket: Option<PathBuf>,
+    pub version: String,
+    /// Lets another launch attach to this instance's server.
+    pub secret: String,
+}
+
+/// Advisory lock on the discobot state directory, held while the app runs
+/// the sidecar so two builds never share one data directory. The OS drops it
+/// if the app crashes, which is how stale locks are recognized.
+#[derive(Default)]
+pub struct InstanceLock(Mutex<Option<Owned>>);
+
+struct Owned {
+    /// Never read; holding it open keeps the lock.
+    _file: File,
+    info: Option<LockInfo>,
+}
+
+/// Another running instance holds the lock.
+pub struct Holder {
+    /// `None` if it hasn't recorded itself yet or the file is unreadable.
+    info: Option<LockInfo>,
+}
+
+pub enum LockError {
+    Held(Holder),
+    Io(String),
+}
+
+fn get_state_dir() -> Result<PathBuf, String> {
+    let state_dir = dirs::state_dir()
+        .or_else(dirs::data_dir)
+        .ok_or_else(|| "Could not determine state directory".to_string())?;
+    Ok(state_dir.join("discobot"))
+}
+
+fn read_info() -> Option<LockInfo> {
+    let path = get_state_dir().ok()?.join("instance.json");
+    let contents = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+fn write_info(info: &LockInfo) -> Result<(), String> {
+    let path = get_state_dir()?.join("instance.json");
+    let contents = serde_json::to_string(info)
+        .map_err(|e| format!("Failed to serialize instance info: {}", e))?;
+    let mut options = File::options();
+    options.write(true).create(true).truncate(true);
+    // It holds the server secret
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(&path)
+        .and_then(|mut file| file.write_all(contents.as_bytes()))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Take the instance lock, cleaning up after an instance that crashed
+/// holding it.
+pub fn acquire() -> Result<InstanceLock, LockError> {
+    let dir = get_state_dir().map_err(LockError::Io)?;
+    fs::create_dir_all(&dir)
+        .map_err(|e| LockError::Io(format!("Failed to create state directory: {}", e)))?;
+    let path = dir.join("instance.lock");
+    let file = File::options()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)
+        .map_err(|e| LockError::Io(format!("Failed to open {}: {}", path.display(), e)))?;
+
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => return Err(LockError::Held(Holder { info: read_info() })),
+        Err(TryLockError::Error(e)) => {
+            return Err(LockError::Io(format!(
+                "Failed to lock {}: {}",
+                path.display(),
+                e
+            )))
+        }
+    }
+
+    if let Some(stale) = read_info() {
+        info!(
+            "Removing the instance lock of Discobot {} (pid {}), which is no longer running",
+            stale.version, stale.pid
+        );
+        let _ = fs::remove_file(dir.join("instance.json"));
+    }
+    Ok(InstanceLock(Mutex::new(Some(Owned {
+        _file: file,
+        info: None,
+    }))))
+}
+
+impl InstanceLock {
+    /// Record this instance's server for other launches. Does nothing if the
+    /// lock isn't held.
+    pub fn record(&self, info: LockInfo) {
+        let mut owned = self.0.lock().unwrap();
+        let Some(owned) = owned.as_mut() else {
+            return;
+        };
+        if let Err(e) = write_info(&info) {
+            warn!("{}", e);
+        }
+        owned.info = Some(info);
+    }
+
+    /// Update the recorded port once the server reports the one it bound.
+    pub fn update_port(&self, port: u16) {
+        let info = {
+            let owned = self.0.lock().unwrap();
+            match owned.as_ref().and_then(|owned| owned.info.as_ref()) {
+                Some(info) if info.port != port => LockInfo {
+                    port,
+                    ..info.clone()
+                },
+                _ => return,
+            }
+        };
+        self.record(info);
+    }
+
+    /// Update the recorded SSH port once the server reports the one it bound.
+    pub fn update_ssh_port(&self, ssh_port: u16) {
+        let info = {
+            let owned = self.0.lock().unwrap();
+            match owned.as_ref().and_then(|owned| owned.info.as_ref()) {
+                Some(info) if info.ssh_port != ssh_port => LockInfo {
+                    ssh_port,
+                    ..info.clone()
+                },
+                _ => return,
+            }
+        };
+        self.record(info);
+    }
+
+    /// Update the recorded secret after it's rotated.
+    pub fn update_secret(&self, secret: &Secret) {
+        let info = {
+            let owned = self.0.lock().unwrap();
+            match owned.as_ref().and_then(|owned| owned.info.as_ref()) {
+                Some(info) => LockInfo {
+                    secret: secret.expose().to_string(),
+                    ..info.clone()
+                },
+                None => return,
+            }
+        };
+        self.record(info);
+    }
+
+    /// Release the lock on the way out. The lock file itself stays, so a
+    /// launch opening it concurrently can't end up locking a deleted file.
+    pub fn release(&self) {
+        if self.0.lock().unwrap().take().is_some() {
+            let _ = get_state_dir().map(|dir| fs::remove_file(dir.join("instance.json")));
+        }
+    }
+}
+
+impl Holder {
+    pub fn describe(&self) -> String {
+        match &self.info {
+            Some(info) => format!(
+                "Discobot {} (pid {}) is already running with the same data directory",
+                info.version, info.pid
+            ),
+            None => "Another Discobot is already running with the same data directory".to_string(),
+        }
+    }
+
+    /// Offer to attach to the other instance's server instead of starting a
+    /// second one, or to quit.
+    pub fn show_dialog(self, app: &tauri::AppHandle) {
+        let message = format!(
+            "{}. Running two servers on one data directory can corrupt it.",
+            self.describe()
+        );
+        let buttons = match &self.info {
+            Some(_) => MessageDialogButtons::OkCancelCustom(
+                USE_EXISTING_BUTTON.to_string(),
+                QUIT_BUTTON.to_string(),
+            ),
+            None => MessageDialogButtons::OkCustom(QUIT_BUTTON.to_string()),
+        };
+        let handle = app.clone();
+        app.dialog()
+            .message(message)
+            .title("Discobot is already running")
+            .kind(MessageDialogKind::Warning)
+            .buttons(buttons)
+            .show_with_result(move |result| match (result, self.info) {
+                (MessageDialogResult::Custom(button), Some(info))
+                    if button == USE_EXISTING_BUTTON =>
+                {
+                    adopt(&handle, info)
+                }
+                _ => handle.exit(0),
+            });
+    }
+}
+
+/// Attach to another instance's server as if it were an external one.
+fn adopt(app: &tauri::AppHandle, info: LockInfo) {
+    info!(
+        "Attaching to the server of Discobot {} (pid {}) on port {}",
+        info.version, info.pid, info.port
+    );
+    {
+        let state = app.state::<Mutex<ServerState>>();
+        let mut state = state.lock().unwrap();
+        state.host = "127.0.0.1".to_string();
+        state.port = info.port;
+        if info.ssh_port != 0 {
+            state.ssh_port = info.ssh_port;
+        }
+        state.socket = info.socket;
+        state.secret = Secret::from(info.secret);
+        app.state::<Redactor>().add(&state.secret);
+        state.external = true;
+        state.error = None;
+        state.env = None;
+        // Held for a sidecar this instance won't start
+        state.listener = None;
+        state.ssh_listener = None;
+    }
+    set_server_status(app, ServerStatus::External);
+    secret_access::allow_secret_refetch(app);
+    let _ = app.emit(SERVER_PORT_EVENT, PortChanged { port: info.port });
+    app_ready::mark_server_ready(app);
+}
@@ -0,0 +1,53 @@
+use std::fs;
+use std::path::PathBuf;
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+/// The ports the last healthy sidecar ran on, tried first on the next launch
+/// so the server URL stays stable (release builds only).
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LastPorts {
+    pub port: u16,
+    pub ssh_port: u16,
+}
+
+fn get_state_path() -> Result<PathBuf, String> {
+    let state_dir = dirs::state_dir()
+        .or_else(dirs::data_dir)
+        .ok_or_else(|| "Could not determine state directory".to_string())?;
+    Ok(state_dir.join("discobot").join("ports.json"))
+}
+
+/// Read the last ports, if any. A corrupt file is treated as missing.
+pub fn load() -> Option<LastPorts> {
+    let path = get_state_path().ok()?;
+    let contents = fs::read_to_string(&path).ok()?;
+    serde_json::from_str(&contents)
+        .inspect_err(|e| warn!("Ignoring invalid {}: {}", path.display(), e))
+        .ok()
+}
+
+pub fn save(ports: LastPorts) -> Result<(), String> {
+    if load() == Some(ports) {
+        return Ok(());
+    }
+    let path = get_state_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create state directory: {}", e))?;
+    }
+    let contents =
+        serde_json::to_string(&ports).map_err(|e| format!("Failed to serialize ports: {}", e))?;
+    fs::write(&path, contents).map_err(|e| format!("Failed to write ports: {}", e))
+}
+
+/// Forget the remembered ports so the next launch picks random ones.
+#[tauri::command]
+pub fn clear_preferred_port() -> Result<(), String> {
+    let path = get_state_path()?;
+    match fs::remove_file(&path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("Failed to remove {}: {}", path.display(), e)),
+    }
+}
@@ -1,38 +1,330 @@
-#[cfg(not(debug_assertions))]
-use std::net::TcpListener;
-use std::sync::Mutex;
+mod api_proxy;
+mod app_dirs;
+mod app_ready;
+mod args;
+mod bind_info;
+mod cors;
+#[cfg(target_os = "macos")]
+mod dock;
+mod external;
+mod features;
+mod first_run;
+mod health;
+mod idle;
+mod instance_lock;
+mod last_ports;
+mod locale;
+mod logs;
+mod network;
+mod orphans;
+mod ports;
+mod process_tree;
+mod proxy;
+mod quit;
+mod resources;
+mod secret;
+mod secret_access;
+mod secret_store;
+mod server_env;
+mod server_socket;
+mod settings;
+mod shell_log;
+mod sidecar;
+mod signals;
+mod startup_metrics;
+mod structured_logs;
+mod tray_theme;
+mod user_secrets;
+#[cfg(target_os = "macos")]
+mod vm;
+mod wake;
+mod watchdog;
 
-#[cfg(not(debug_assertions))]
-use std::fs;
-#[cfg(not(debug_assertions))]
+use std::net::{SocketAddr, TcpListener};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-#[cfg(not(debug_assertions))]
 use tauri_plugin_shell::ShellExt;
 
-#[cfg(not(debug_assertions))]
-use rand::Rng;
+use log::{debug, error, info, warn};
+use serde::Serialize;
 use tauri::{
     menu::{Menu, MenuItem},
     tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
-    Manager, WindowEvent,
+    Emitter, Manager, RunEvent, WebviewWindowBuilder, WindowEvent,
 };
-#[cfg(not(debug_assertions))]
-use tauri_plugin_shell::process::CommandChild;
-use tauri_plugin_window_state::StateFlags;
+use tauri_plugin_deep_link::DeepLinkExt;
+use tauri_plugin_notification::NotificationExt;
+use tauri_plugin_shell::process::{CommandChild, CommandEvent};
+use tauri_plugin_window_state::{AppHandleExt, StateFlags};
+
+use secret::Secret;
+use settings::{CloseBehavior, Settings};
 
 fn window_state_flags() -> StateFlags {
     // Save all state except decorations (we manage those ourselves)
     StateFlags::all() - StateFlags::DECORATIONS
 }
 
+/// Center the window on the primary display if the position the window-state
+/// plugin restored is off every connected monitor, e.g. after undocking from
+/// an external one.
+fn ensure_on_screen(window: &tauri::WebviewWindow) {
+    let (Ok(position), Ok(size), Ok(monitors)) = (
+        window.outer_position(),
+        window.outer_size(),
+        window.available_monitors(),
+    ) else {
+        return;
+    };
+    let on_screen = monitors.iter().any(|monitor| {
+        let (origin, extent) = (monitor.position(), monitor.size());
+        position.x < origin.x + extent.width as i32
+            && position.x + size.width as i32 > origin.x
+            && position.y < origin.y + extent.height as i32
+            && position.y + size.height as i32 > origin.y
+    });
+    if on_screen || monitors.is_empty() {
+        return;
+    }
+    let Ok(Some(primary)) = window.primary_monitor() else {
+        return;
+    };
+    let (origin, extent) = (primary.position(), primary.size());
+    // A window larger than the display keeps its title bar on screen
+    let centered = tauri::PhysicalPosition::new(
+        origin.x + (extent.width.saturating_sub(size.width) / 2) as i32,
+        origin.y + (extent.height.saturating_sub(size.height) / 2) as i32,
+    );
+    warn!(
+        "Window was restored off-screen at {},{}, moving it to the primary display",
+        position.x, position.y
+    );
+    let _ = window.set_position(centered);
+}
+
+/// Size of the main window before the user resizes it, matching
+/// `tauri.conf.json`.
+const DEFAULT_WINDOW_SIZE: tauri::LogicalSize<f64> = tauri::LogicalSize::new(1200.0, 1200.0);
+
+/// Put the main window back at its default size, centered, and forget the
+/// saved geometry. The manual fix for a window restored tiny or somewhere
+/// `ensure_on_screen` doesn't catch.
+#[tauri::command]
+fn reset_window_state(app: tauri::AppHandle) -> Result<(), String> {
+    let window = app
+        .get_webview_window("main")
+        .ok_or_else(|| "Main window not found".to_string())?;
+    window
+        .unmaximize()
+        .and_then(|_| window.set_fullscreen(false))
+        .and_then(|_| window.set_size(DEFAULT_WINDOW_SIZE))
+        .and_then(|_| window.center())
+        .map_err(|e| format!("Failed to reset window: {}", e))?;
+
+    let path = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| format!("Failed to get config directory: {}", e))?
+        .join(app.filename());
+    match std::fs::remove_file(&path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("Failed to remove {}: {}", path.display(), e)),
+    }
+}
+
+/// Port the dev frontend expects the Go server on (`pnpm dev:api`), also used
+/// for a sidecar spawned in dev builds.
+const DEV_PORT: u16 = 3001;
+
+/// The server's default SSH port, used unless it's taken.
+const DEFAULT_SSH_PORT: u16 = 3333;
+
+/// Set to `1` in dev builds to spawn the sidecar instead of expecting the
+/// server to be started separately.
+const SPAWN_SIDECAR_ENV: &str = "DISCOBOT_SPAWN_SIDECAR";
+
+/// Overrides `Settings::fixed_port`.
+const PORT_ENV: &str = "DISCOBOT_PORT";
+
+/// Number of times a crashed sidecar is respawned before giving up.
+const MAX_RESTART_ATTEMPTS: u32 = 3;
+
+/// Number of times a sidecar that couldn't bind its port is respawned on a
+/// fresh one before giving up.
+const MAX_BIND_ATTEMPTS: u32 = 3;
+
+/// A sidecar that stayed up at least this long is considered healthy again,
+/// resetting the restart attempt counter.
+const RESTART_RESET_AFTER: Duration = Duration::from_secs(60);
+
+/// Event emitted whenever the server lifecycle status changes.
+const SERVER_STATUS_EVENT: &str = "server-status";
+
+/// Event emitted when a setting changed that the running server only picks
+/// up when it restarts.
+const SERVER_RESTART_REQUIRED_EVENT: &str = "server-restart-required";
+
+/// Event emitted when the server is restarted to apply changed settings.
+const SERVER_RESTARTING_EVENT: &str = "server-restarting";
+
+/// Event emitted whenever a sidecar reports it's ready, including after a
+/// restart for changed settings.
+const SERVER_READY_EVENT: &str = "server-ready";
+
+/// Settings changes this close together are applied with a single restart.
+const SETTINGS_RESTART_DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// Event emitted when the sidecar reports a different port than requested,
+/// or is respawned on a fresh one because its port was taken.
+const SERVER_PORT_EVENT: &str = "server-port-changed";
+
+/// Printed to stdout, followed by the bound port, once the server is
+/// listening. Authoritative even if the server picked its own port.
+const LISTENING_MARKER: &str = "DISCOBOT_LISTENING port=";
+
+/// Printed to stdout, followed by the address the server bound (e.g.
+/// `127.0.0.1:4000` or `[::]:4000`), just before `LISTENING_MARKER`.
+const BIND_ADDR_MARKER: &str = "DISCOBOT_LISTENING_ADDR addr=";
+
+/// Printed to stdout, followed by the socket path, once the server is
+/// listening on a Unix socket.
+const SOCKET_LISTENING_MARKER: &str = "DISCOBOT_LISTENING socket=";
+
+/// Printed to stdout, followed by the bound port, once the SSH server is
+/// listening.
+const SSH_LISTENING_MARKER: &str = "DISCOBOT_SSH_LISTENING port=";
+
+/// Logged by the server, followed by the port, when it can't bind it, e.g.
+/// because another process took it after the app released it.
+const BIND_FAILURE_MARKER: &str = "Failed to listen on port ";
+
+/// Older servers only log this, followed by the requested port, once
+/// migrations and provider setup are done.
+const READY_MARKER: &str = "Server starting on port ";
+
+/// How long the sidecar gets to report its port before startup is considered
+/// failed. Generous because database migrations run first.
+const READY_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Serialize)]
+struct PortChanged {
+    port: u16,
+}
+
+/// Event emitted for each `discobot://` URL the app is asked to open.
+const OPEN_DEEP_LINK_EVENT: &str = "open-deep-link";
+
+#[derive(Clone, Serialize)]
+struct DeepLink {
+    url: String,
+}
+
+/// Event emitted when the window is closed with `CloseBehavior::Ask`; the
+/// frontend prompts and calls `hide_main_window` or `quit_app`.
+const CLOSE_REQUESTED_EVENT: &str = "close-requested";
+
+/// Event emitted when the main window is shown, hidden, focused or blurred.
+const WINDOW_VISIBILITY_EVENT: &str = "window-visibility-changed";
+
+#[derive(Clone, Serialize)]
+struct WindowVisibility {
+    visible: bool,
+    focused: bool,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+enum ServerStatus {
+    /// The sidecar is being prepared and spawned in the background.
+    Starting,
+    /// The sidecar was spawned and is listening on `port`.
+    Running { port: u16 },
+    /// The sidecar could not be started.
+    Failed { error: String },
+    /// The server is managed outside the app (dev mode or an external server).
+    External,
+    /// The sidecar was stopped while the app was idle and starts again when
+    /// the window is shown.
+    Suspended,
+    /// The sidecar was stopped by the user and starts again on request.
+    Stopped,
+}
+
 struct ServerState {
+    /// Where the server listens: loopback for the sidecar, or the external
+    /// server's host.
+    host: String,
+    /// Unused (0) while serving on `socket`.
     port: u16,
-    secret: String,
-    /// Held to keep the sidecar's stdin pipe open (server exits when stdin closes).
-    #[cfg(not(debug_assertions))]
-    #[allow(dead_code)]
+    /// Unix socket the sidecar listens on instead of a TCP port.
+    socket: Option<PathBuf>,
+    /// Address the sidecar reported listening on, kept across restarts so
+    /// each new address is only checked once.
+    bind_addr: Option<SocketAddr>,
+    /// Fixed port that was taken at launch, so `port` is a random one.
+    busy_port: Option<u16>,
+    /// Range the ports were allocated from, if one is configured.
+    port_range: Option<settings::PortRange>,
+    ssh_port: u16,
+    /// `DEFAULT_SSH_PORT` if it was taken at launch, so `ssh_port` differs.
+    busy_ssh_port: Option<u16>,
+    secret: Secret,
+    status: ServerStatus,
+    /// The server port, bound by the app and held until the next sidecar takes
+    /// it over, so no other process can grab it in between.
+    listener: Option<TcpListener>,
+    /// The SSH port, held the same way.
+    ssh_listener: Option<TcpListener>,
+    /// Held to keep the sidecar's stdin pipe open (server exits when stdin
+    /// closes) and to write control commands to it.
     process: Option<CommandChild>,
+    /// Whether the current sidecar is running. Each sidecar gets its own flag,
+    /// which its supervisor clears when the process terminates.
+    alive: Arc<AtomicBool>,
+    /// Background task preparing and spawning the sidecar, if still in flight.
+    startup: Option<tauri::async_runtime::JoinHandle<()>>,
+    /// Consecutive crash restarts since the sidecar last ran stably.
+    restart_attempts: u32,
+    /// Consecutive respawns on a fresh port after the sidecar failed to bind.
+    bind_attempts: u32,
+    /// Set once the app is quitting so sidecar exits aren't treated as crashes.
+    shutting_down: bool,
+    /// Why the sidecar last failed to start, cleared once it reports ready.
+    error: Option<String>,
+    /// Whether sidecar output is forwarded to the webview as `server-log` events.
+    forward_logs: bool,
+    /// Attached to a server started outside the app, so there is no sidecar
+    /// to spawn, restart or kill.
+    external: bool,
+    /// Stopped by the idle monitor; resumed on the same port and secret.
+    suspended: bool,
+    /// A setting changed since the running sidecar was spawned.
+    restart_required: bool,
+    /// Bumped by every settings change so only the last of a burst restarts
+    /// the server.
+    settings_changes: u64,
+    /// Incremented for every spawned sidecar so a superseded supervisor can
+    /// tell its process was replaced rather than crashed.
+    generation: u64,
+    /// Startup milestones of the current sidecar, for `get_startup_metrics`.
+    startup_times: startup_metrics::StartupTimes,
+    /// What the current sidecar was started with, for `get_server_env`.
+    env: Option<server_env::ServerEnv>,
+}
+
+impl ServerState {
+    /// Where the shell's own requests, like health checks, reach the server.
+    fn endpoint(&self) -> health::Endpoint {
+        health::Endpoint {
+            host: self.host.clone(),
+            port: self.port,
+            socket: self.socket.clone(),
+        }
+    }
 }
 
 #[tauri::command]
@@ -40,9 +332,226 @@ fn get_server_port(state: tauri::State<'_, Mutex<ServerState>>) -> u16 {
     state.lock().unwrap().port
 }
 
+/// The effective server and SSH ports and, where they differ, the ports that
+/// were busy, so the UI can explain why they changed.
+#[derive(Serialize)]
+struct ServerPortInfo {
+    port: u16,
+    busy_port: Option<u16>,
+    ssh_port: u16,
+    busy_ssh_port: Option<u16>,
+    port_range: Option<settings::PortRange>,
+}
+
+impl ServerPortInfo {
+    fn new(state: &ServerState) -> Self {
+        Self {
+            port: state.port,
+            busy_port: state.busy_port,
+            ssh_port: state.ssh_port,
+            busy_ssh_port: state.busy_ssh_port,
+            port_range: state.port_range,
+        }
+    }
+}
+
+#[tauri::command]
+fn get_server_port_info(state: tauri::State<'_, Mutex<ServerState>>) -> ServerPortInfo {
+    ServerPortInfo::new(&state.lock().unwrap())
+}
+
+/// Base URL of the server, e.g. `http://127.0.0.1:4000`, or
+/// `discobot-api://localhost` while it listens on a socket. With `include_secret`
+/// the secret is added as the `token` query parameter, for opening the URL in a
+/// browser. A server suspended while idle is resumed first. Fails while the
+/// server isn't running rather than returning a URL that may change; wait for
+/// `server-ready` instead.
+#[tauri::command]
+async fn get_server_url(
+    app: tauri::AppHandle,
+    include_secret: Option<bool>,
+) -> Result<String, String> {
+    idle::wake(&app).await;
+    let state = app.state::<Mutex<ServerState>>();
+    let state = state.lock().unwrap();
+    if !matches!(
+        state.status,
+        ServerStatus::Running { .. } | ServerStatus::External
+    ) {
+        return Err("The server isn't running".to_string());
+    }
+
+    let url = if state.socket.is_some() {
+        api_proxy::base_url()
+    } else if state.host.contains(':') {
+        format!("http://[{}]:{}", state.host, state.port)
+    } else {
+        format!("http://{}:{}", state.host, state.port)
+    };
+    if include_secret.unwrap_or(false) && !state.secret.is_empty() {
+        // An external server's secret may need escaping
+        let mut url =
+            tauri::Url::parse(&url).map_err(|e| format!("Failed to build server URL: {}", e))?;
+        url.query_pairs_mut()
+            .append_pair("token", state.secret.expose());
+        return Ok(url.to_string());
+    }
+    Ok(url)
+}
+
+/// The SSH port of the server in use, including one attached to from another
+/// instance.
+#[tauri::command]
+fn get_ssh_port(state: tauri::State<'_, Mutex<ServerState>>) -> u16 {
+    state.lock().unwrap().ssh_port
+}
+
+/// Ready-to-paste command for connecting to a session over SSH, e.g.
+/// `ssh -p 3333 <session_id>@127.0.0.1`.
+#[tauri::command]
+fn get_ssh_command(
+    settings: tauri::State<'_, Mutex<Settings>>,
+    state: tauri::State<'_, Mutex<ServerState>>,
+    session_id: String,
+) -> Result<String, String> {
+    if !settings.lock().unwrap().feature_flags.ssh {
+        return Err("The SSH server is disabled".to_string());
+    }
+    // Pasted into a shell, so only plain IDs are accepted
+    let valid = !session_id.is_empty()
+        && session_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(format!("Invalid session ID '{}'", session_id));
+    }
+    let state = state.lock().unwrap();
+    Ok(format!(
+        "ssh -p {} {}@{}",
+        state.ssh_port, session_id, state.host
+    ))
+}
+
+/// Whether the app's sidecar process is running. Always false for an external
+/// server, whose process the app can't see.
+#[tauri::command]
+fn is_server_running(state: tauri::State<'_, Mutex<ServerState>>) -> bool {
+    state.lock().unwrap().alive.load(Ordering::SeqCst)
+}
+
+#[derive(Serialize)]
+struct ServerStatusInfo {
+    #[serde(flatten)]
+    status: ServerStatus,
+    /// Set while a self-built server runs instead of the bundled one.
+    server_path_override: Option<String>,
+    ports: ServerPortInfo,
+}
+
+#[tauri::command]
+fn get_server_status(
+    state: tauri::State<'_, Mutex<ServerState>>,
+    settings: tauri::State<'_, Mutex<Settings>>,
+) -> ServerStatusInfo {
+    let server_path = settings.lock().unwrap().server_path.clone();
+    let server_path_override = sidecar::path_override(server_path.as_deref())
+        .ok()
+        .flatten()
+        .map(|path| path.display().to_string());
+    let state = state.lock().unwrap();
+    ServerStatusInfo {
+        status: state.status.clone(),
+        server_path_override,
+        ports: ServerPortInfo::new(&state),
+    }
+}
+
+#[tauri::command]
+fn get_server_error(state: tauri::State<'_, Mutex<ServerState>>) -> Option<String> {
+    state.lock().unwrap().error.clone()
+}
+
+/// Diagnostic snapshot of the server for bug reports.
+#[derive(Serialize)]
+struct ServerInfo {
+    port: u16,
+    ssh_port: u16,
+    /// Whether the app is currently running a sidecar process.
+    process_alive: bool,
+    log_path: Option<String>,
+    /// The current run's log files, then earlier runs', newest first.
+    log_files: Vec<String>,
+    build_profile: &'static str,
+    restart_required: bool,
+    /// First 4 characters only, enough to tell secrets apart.
+    secret: String,
+}
+
+#[tauri::command]
+fn get_server_info(state: tauri::State<'_, Mutex<ServerState>>) -> ServerInfo {
+    let state = state.lock().unwrap();
+    let secret = state.secret.expose();
+    let secret = match secret.char_indices().nth(4) {
+        Some((end, _)) => format!("{}…", &secret[..end]),
+        None => secret.to_string(),
+    };
+
+    ServerInfo {
+        port: state.port,
+        ssh_port: state.ssh_port,
+        process_alive: state.process.is_some(),
+        log_path: logs::get_current_log_file_path()
+            .ok()
+            .map(|path| path.display().to_string()),
+        log_files: logs::log_files()
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect(),
+        build_profile: if cfg!(debug_assertions) {
+            "debug"
+        } else {
+            "release"
+        },
+        restart_required: state.restart_required,
+        secret,
+    }
+}
+
+/// App and server versions for the About dialog. The server fields are
+/// `None` if it can't be reached.
+#[derive(Serialize)]
+struct VersionInfo {
+    app_version: String,
+    tauri_version: &'static str,
+    server_version: Option<String>,
+    server_commit: Option<String>,
+}
+
+#[tauri::command]
+async fn get_versions(app: tauri::AppHandle) -> VersionInfo {
+    let endpoint = app.state::<Mutex<ServerState>>().lock().unwrap().endpoint();
+    let server = health::server_version(&endpoint).await;
+
+    VersionInfo {
+        app_version: app.package_info().version.to_string(),
+        tauri_version: tauri::VERSION,
+        server_version: server.as_ref().map(|server| server.version.clone()),
+        server_commit: server.and_then(|server| server.commit),
+    }
+}
+
+/// Enable or disable forwarding of sidecar output to the webview (off by default).
 #[tauri::command]
-fn get_server_secret(state: tauri::State<'_, Mutex<ServerState>>) -> String {
-    state.lock().unwrap().secret.clone()
+fn set_server_log_forwarding(
+    state: tauri::State<'_, Mutex<ServerState>>,
+    enabled: bool,
+) -> Result<(), String> {
+    let mut state = state.lock().unwrap();
+    if state.external {
+        return Err("Output of an external server isn't captured".to_string());
+    }
+    state.forward_logs = enabled;
+    Ok(())
 }
 
 #[tauri::command]
@@ -55,16 +564,80 @@ fn save_file_to_downloads(filename: String, content: String) -> Result<String, S
     Ok(path.to_string_lossy().to_string())
 }
 
+/// Show a native notification, unless the user has disabled them.
+fn notify(app: &tauri::AppHandle, title: &str, body: &str) {
+    let settings = app.state::<Mutex<Settings>>();
+    if !settings.lock().unwrap().notifications_enabled {
+        return;
+    }
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        warn!("Failed to show notification: {}", e);
+    }
+}
+
+/// Tell the frontend about the main window's visibility. Called after the
+/// activation policy has been updated so both always agree.
+fn emit_window_visibility(app: &tauri::AppHandle, visible: bool) {
+    let focused = visible
+        && app
+            .get_webview_window("main")
+            .and_then(|window| window.is_focused().ok())
+            .unwrap_or(false);
+    let _ = app.emit(
+        WINDOW_VISIBILITY_EVENT,
+        WindowVisibility { visible, focused },
+    );
+}
+
+fn main_window_visible(app: &tauri::AppHandle) -> bool {
+    app.get_webview_window("main")
+        .is_some_and(|window| window.is_visible().unwrap_or(false))
+}
+
+/// Show or hide the tray icon and remember the choice. The tray can't be
+/// hidden while the window is, since there'd be no way back to the app short
+/// of launching it again.
+#[tauri::command]
+fn set_tray_visible(app: tauri::AppHandle, visible: bool) -> Result<(), String> {
+    let tray = app
+        .tray_by_id(TRAY_ID)
+        .ok_or_else(|| "There is no tray icon".to_string())?;
+    if !visible && !main_window_visible(&app) {
+        return Err("Show the window before hiding the tray icon".to_string());
+    }
+    {
+        let state = app.state::<Mutex<Settings>>();
+        let mut current = state.lock().unwrap();
+        let mut updated = current.clone();
+        updated.show_tray_icon = visible;
+        settings::save(&updated)?;
+        *current = updated;
+    }
+    tray.set_visible(visible)
+        .map_err(|e| format!("Failed to update tray icon: {}", e))
+}
+
+#[tauri::command]
+fn get_tray_visible(settings: tauri::State<'_, Mutex<Settings>>) -> bool {
+    settings.lock().unwrap().show_tray_icon
+}
+
 fn show_window(app: &tauri::AppHandle) {
     if let Some(window) = app.get_webview_window("main") {
         #[cfg(target_os = "macos")]
         {
             use tauri::ActivationPolicy;
             let _ = app.set_activation_policy(ActivationPolicy::Regular);
+            dock::install_menu(app);
         }
+        idle::resume(app);
         let _ = window.show();
         let _ = window.unminimize();
         let _ = window.set_focus();
+        emit_window_visibility(app, true);
+        app_ready::mark_window_shown(app);
+        info!("Window shown");
+        logs::append_server_log("Window shown");
     }
 }
 
@@ -76,9 +649,180 @@ fn hide_window(app: &tauri::AppHandle) {
             use tauri::ActivationPolicy;
             let _ = app.set_activation_policy(ActivationPolicy::Accessory);
         }
+        emit_window_visibility(app, false);
+        // Without the tray icon (or, on macOS, the Dock icon) there'd be no
+        // way back to the window
+        let show_tray = app
+            .state::<Mutex<Settings>>()
+            .lock()
+            .unwrap()
+            .show_tray_icon;
+        if let Some(tray) = app.tray_by_id(TRAY_ID).filter(|_| !show_tray) {
+            info!("Showing the tray icon since the window is hidden");
+            let _ = tray.set_visible(true);
+        }
+        info!("Window hidden");
+        logs::append_server_log("Window hidden");
     }
 }
 
+/// Stdout lines starting with this prefix are replies to control commands
+/// rather than regular log output.
+const CONTROL_REPLY_PREFIX: &str = "@control ";
+
+/// Log levels accepted by `set_server_log_level`.
+const LOG_LEVELS: [&str; 4] = ["debug", "info", "warn", "error"];
+
+const DEFAULT_LOG_LEVEL: &str = "info";
+
+/// `level` in lowercase if it's one of `LOG_LEVELS`.
+fn validate_log_level(level: &str) -> Result<String, String> {
+    let level = level.to_lowercase();
+    if !LOG_LEVELS.contains(&level.as_str()) {
+        return Err(format!(
+            "Invalid log level '{}', expected one of: {}",
+            level,
+            LOG_LEVELS.join(", ")
+        ));
+    }
+    Ok(level)
+}
+
+/// Write one newline-delimited JSON command to the sidecar's stdin.
+fn write_server_command(
+    state: &mut ServerState,
+    command: &serde_json::Value,
+) -> Result<(), String> {
+    let mut line =
+        serde_json::to_vec(command).map_err(|e| format!("Failed to encode command: {}", e))?;
+    line.push(b'\n');
+
+    if state.external {
+        return Err("The server is managed externally".to_string());
+    }
+    let child = state
+        .process
+        .as_mut()
+        .ok_or_else(|| "Server is not running".to_string())?;
+    child
+        .write(&line)
+        .map_err(|e| format!("Failed to write to server stdin: {}", e))
+}
+
+/// Send a control command to the sidecar over its stdin.
+///
+/// Framing is newline-delimited JSON: each command is a single JSON value
+/// written on one line. `json` is re-serialized compactly so embedded
+/// newlines can't split a message. Replies arrive on stdout as lines prefixed
+/// with `@control ` and are logged (and forwarded) under the `control` stream.
+///
+/// The server understands `{"cmd":"status"}`, `{"cmd":"gc"}`,
+/// `{"cmd":"dump_goroutines"}` and `{"cmd":"set_log_level","level":"debug"}`;
+/// an `"id"` is echoed in the reply.
+#[tauri::command]
+fn send_server_command(
+    state: tauri::State<'_, Mutex<ServerState>>,
+    json: String,
+) -> Result<(), String> {
+    let command: serde_json::Value =
+        serde_json::from_str(&json).map_err(|e| format!("Invalid command JSON: {}", e))?;
+
+    // Writing while holding the lock keeps concurrent commands from interleaving.
+    write_server_command(&mut state.lock().unwrap(), &command)
+}
+
+/// Maintenance actions `server_control` may ask the server to perform.
+const CONTROL_ACTIONS: [&str; 2] = ["gc", "status"];
+
+/// Ask the running server to perform a maintenance action, e.g. `gc`, over
+/// HTTP rather than stdin so it gets a reply. Returns the server's JSON reply.
+/// A server suspended while idle is resumed first.
+#[tauri::command]
+async fn server_control(app: tauri::AppHandle, action: String) -> Result<String, String> {
+    if !CONTROL_ACTIONS.contains(&action.as_str()) {
+        return Err(format!(
+            "Invalid control action '{}', expected one of: {}",
+            action,
+            CONTROL_ACTIONS.join(", ")
+        ));
+    }
+    idle::wake(&app).await;
+    let (endpoint, secret) = {
+        let state = app.state::<Mutex<ServerState>>();
+        let state = state.lock().unwrap();
+        if !matches!(
+            state.status,
+            ServerStatus::Running { .. } | ServerStatus::External
+        ) {
+            return Err("The server isn't running".to_string());
+        }
+        (state.endpoint(), state.secret.clone())
+    };
+    health::control(&endpoint, secret.expose(), &action).await
+}
+
+/// Change and save the sidecar's log level. A running sidecar switches over
+/// right away through a `set_log_level` control command, without a restart;
+/// later starts get it as `DISCOBOT_LOG_LEVEL`.
+#[tauri::command]
+fn set_server_log_level(app: tauri::AppHandle, level: String) -> Result<(), String> {
+    let level = validate_log_level(&level)?;
+    {
+        let state = app.state::<Mutex<ServerState>>();
+        let mut state = state.lock().unwrap();
+        if state.external {
+            return Err("The server is managed externally".to_string());
+        }
+        if state.process.is_some() {
+            let command = serde_json::json!({ "cmd": "set_log_level", "level": level });
+            write_server_command(&mut state, &command)?;
+        }
+    }
+    let state = app.state::<Mutex<Settings>>();
+    let mut current = state.lock().unwrap();
+    if current.log_level != level {
+        let mut updated = current.clone();
+        updated.log_level = level;
+        settings::save(&updated)?;
+        *current = updated;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn get_server_log_level(settings: tauri::State<'_, Mutex<Settings>>) -> String {
+    settings.lock().unwrap().log_level.clone()
+}
+
+/// Whether this is a debug build, where the dev server is started by hand
+/// and the app doesn't manage it unless `DISCOBOT_SPAWN_SIDECAR=1`.
+#[tauri::command]
+fn is_dev_build() -> bool {
+    cfg!(debug_assertions)
+}
+
+#[tauri::command]
+fn is_window_visible(app: tauri::AppHandle) -> bool {
+    app.get_webview_window("main")
+        .map(|window| window.is_visible().unwrap_or(false))
+        .unwrap_or(false)
+}
+
+#[tauri::command]
+fn show_main_window(app: tauri::AppHandle) {
+    show_window(&app);
+}
+
+#[tauri::command]
+fn hide_main_window(app: tauri::AppHandle) {
+    hide_window(&app);
+}
+
+#[tauri::command]
+fn quit_app(app: tauri::AppHandle) {
+    quit::request_quit(&app);
+}
+
 fn toggle_window(app: &tauri::AppHandle) {
     if let Some(window) = app.get_webview_window("main") {
         // Check if window is visible and focused
@@ -95,144 +839,1241 @@ fn toggle_window(app: &tauri::AppHandle) {
     }
 }
 
-#[cfg(not(debug_assertions))]
-fn find_available_port() -> u16 {
-    TcpListener::bind("127.0.0.1:0")
-        .expect("Failed to bind to find available port")
-        .local_addr()
-        .expect("Failed to get local address")
-        .port()
+fn spawn_sidecar_in_dev() -> bool {
+    std::env::var(SPAWN_SIDECAR_ENV).is_ok_and(|value| value == "1")
 }
 
-#[cfg(not(debug_assertions))]
-fn generate_secret() -> String {
-    const CHARSET: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
-    let mut rng = rand::rng();
-    (0..32)
-        .map(|_| {
-            let idx = rng.random_range(0..CHARSET.len());
-            CHARSET[idx] as char
-        })
-        .collect()
+/// Listeners bound at launch for the sidecar to take over (or the socket it
+/// should listen on instead), and the preferred ports that were taken.
+#[derive(Default)]
+struct BoundPorts {
+    listener: Option<TcpListener>,
+    socket: Option<PathBuf>,
+    ssh_listener: Option<TcpListener>,
+    busy_port: Option<u16>,
+    busy_ssh_port: Option<u16>,
+    port_range: Option<settings::PortRange>,
 }
 
-#[cfg(not(debug_assertions))]
-fn get_log_file_path() -> Result<PathBuf, String> {
-    // Try XDG_STATE_HOME first, fallback to XDG_DATA_HOME, then ~/.local/state
-    let state_dir = dirs::state_dir()
-        .or_else(|| dirs::data_dir())
-        .ok_or_else(|| "Could not determine state directory".to_string())?;
+/// The fixed server port from `DISCOBOT_PORT` or the settings. An invalid
+/// value is ignored with a warning rather than stopping the app from starting.
+fn fixed_port(setting: Option<u16>) -> Option<u16> {
+    let port = match std::env::var(PORT_ENV) {
+        Ok(value) => match value.trim().parse::<u16>() {
+            Ok(port) => port,
+            Err(e) => {
+                warn!("Ignoring {}={}: {}", PORT_ENV, value, e);
+                return None;
+            }
+        },
+        Err(_) => setting?,
+    };
+    match settings::validate_fixed_port(port) {
+        Ok(()) => Some(port),
+        Err(e) => {
+            warn!("Ignoring fixed port: {}", e);
+            None
+        }
+    }
+}
 
-    let log_dir = state_dir.join("discobot").join("logs");
+/// Bind the server port, trying the preferred port first so the server URL is
+/// usually stable, then the configured range, then a random one.
+fn bind_server_port(preferred: Option<u16>, range: Option<settings::PortRange>) -> TcpListener {
+    ports::bind(preferred, range).expect("Failed to bind to find available port")
+}
 
-    // Create the directory if it doesn't exist
-    fs::create_dir_all(&log_dir).map_err(|e| format!("Failed to create log directory: {}", e))?;
+/// Bind the SSH port, trying the last launch's port, then the default, then
+/// the configured range or a random one. Held until the sidecar takes it
+/// over, like the server port.
+fn bind_ssh_port(last: Option<u16>, range: Option<settings::PortRange>) -> Option<TcpListener> {
+    let in_range = |port: &u16| range.is_none_or(|range| range.contains(*port));
+    let preferred = [last, Some(DEFAULT_SSH_PORT)].into_iter().flatten();
+    ports::bind(preferred.filter(in_range), range)
+}
 
-    Ok(log_dir.join("server.log"))
+/// Once the server answers its health check, save its ports as the ones to
+/// try next launch. Dev builds use fixed ports, so there's nothing to save.
+fn remember_ports(app: &tauri::AppHandle) {
+    if cfg!(debug_assertions) {
+        return;
+    }
+    let (endpoint, ssh_port) = {
+        let state = app.state::<Mutex<ServerState>>();
+        let state = state.lock().unwrap();
+        // There's no TCP port to remember while serving on a socket
+        if state.socket.is_some() {
+            return;
+        }
+        (state.endpoint(), state.ssh_port)
+    };
+    let port = endpoint.port;
+    tauri::async_runtime::spawn(async move {
+        if !health::check_health(&endpoint).await {
+            return;
+        }
+        if let Err(e) = last_ports::save(last_ports::LastPorts { port, ssh_port }) {
+            warn!("Failed to remember server ports: {}", e);
+        }
+    });
+}
+
+/// Why the sidecar couldn't be started.
+enum StartError {
+    /// The sidecar binary is missing, not executable, or failed to spawn,
+    /// which usually means a broken install. `missing` if it wasn't bundled
+    /// for this target at all.
+    Sidecar {
+        path: PathBuf,
+        error: String,
+        missing: bool,
+    },
+    /// Anything else, such as invalid VZ resource overrides.
+    Setup(String),
 }
 
-#[cfg(not(debug_assertions))]
 fn start_server(
     app: &tauri::AppHandle,
     port: u16,
     ssh_port: u16,
     secret: &str,
-) -> Result<CommandChild, String> {
-    let log_path = get_log_file_path()?;
-
-    #[allow(unused_mut)]
-    let mut sidecar = app
-        .shell()
-        .sidecar("discobot-server")
-        .map_err(|e| format!("Failed to create sidecar command: {}", e))?
-        .env("PORT", port.to_string())
+    listener: Option<TcpListener>,
+    ssh_listener: Option<TcpListener>,
+) -> Result<(tauri::async_runtime::Receiver<CommandEvent>, CommandChild), StartError> {
+    let settings = app.state::<Mutex<Settings>>().lock().unwrap().clone();
+    let path_override =
+        sidecar::path_override(settings.server_path.as_deref()).map_err(StartError::Setup)?;
+    let binary =
+        sidecar::profile_binary(settings.server_profile.as_deref()).map_err(StartError::Setup)?;
+    let sidecar_path = match &path_override {
+        Some(path) => path.clone(),
+        None => sidecar::path(&binary).map_err(StartError::Setup)?,
+    };
+    let sidecar_error = |error: String| StartError::Sidecar {
+        path: sidecar_path.clone(),
+        error,
+        missing: false,
+    };
+    let missing = |error: String| StartError::Sidecar {
+        path: sidecar_path.clone(),
+        error,
+        missing: true,
+    };
+    if let Some(path) = &path_override {
+        // Not a broken install, so skip the reinstall advice
+        sidecar::check(path)
+            .map_err(|e| StartError::Setup(format!("{} ({})", e, path.display())))?;
+    } else if binary != sidecar::NAME {
+        sidecar::check_profile(&binary, &sidecar_path).map_err(StartError::Setup)?;
+    } else {
+        sidecar::check(&sidecar_path).map_err(|error| {
+            if sidecar_path.exists() {
+                sidecar_error(error)
+            } else {
+                missing(error)
+            }
+        })?;
+    }
+
+    let mut sidecar = match &path_override {
+        Some(path) => {
+            info!("Using server binary override: {}", path.display());
+            app.shell().command(path)
+        }
+        None => app
+            .shell()
+            .sidecar(&binary)
+            .map_err(|e| missing(format!("Failed to create sidecar command: {}", e)))?,
+    };
+    let inherited_env = server_env::inherited(settings.inherit_environment);
+    let clear_env = inherited_env.is_some();
+    if let Some(inherited) = inherited_env {
+        sidecar = sidecar.env_clear().envs(inherited);
+    }
+    let socket = app
+        .state::<Mutex<ServerState>>()
+        .lock()
+        .unwrap()
+        .socket
+        .clone();
+    sidecar = match socket {
+        Some(socket) => sidecar.env("LISTEN_SOCKET", socket.display().to_string()),
+        None => sidecar.env("PORT", port.to_string()),
+    };
+    sidecar = sidecar
         .env("SSH_PORT", ssh_port.to_string())
-        .env("CORS_ORIGINS", "http://tauri.localhost,tauri://localhost")
+        .env("CORS_ORIGINS", cors::origins(&settings))
         .env("DISCOBOT_SECRET", secret)
+        .env("LOG_LEVEL", &settings.log_level)
+        .env("DISCOBOT_LOG_LEVEL", &settings.log_level)
+        .envs(logs::log_dir().map(|dir| (logs::LOG_DIR_ENV, dir)))
         .env("TAURI", "true")
-        .env("SUGGESTIONS_ENABLED", "true")
         .env("STDIN_KEEPALIVE", "true")
-        .env(
-            "LOG_FILE",
-            log_path.to_string_lossy().to_string(),
-        );
+        .envs(settings.feature_flags.env())
+        .envs(resources::vm_env(&settings))
+        .envs(proxy::env(&settings.proxy))
+        .envs(locale::env(&locale::resolve(settings.language.as_deref())));
 
-    // Check for bundled VZ resources (macOS only)
+    // Sandbox VM resources: explicit overrides win, otherwise fall back to
+    // the verified files bundled with the app
     #[cfg(target_os = "macos")]
     {
-        use tauri::Manager;
-        if let Ok(resource_dir) = app.path().resource_dir() {
-            let vz_dir = resource_dir.join("vz");
-            let kernel_path = vz_dir.join("vmlinux");
-            let rootfs_path = vz_dir.join("discobot-rootfs.squashfs");
-
-            // Check if both files exist
-            if kernel_path.exists() && rootfs_path.exists() {
-                println!("Found bundled VZ resources:");
-                println!("  Kernel: {}", kernel_path.display());
-                println!("  Rootfs: {}", rootfs_path.display());
-
-                sidecar = sidecar
-                    .env("VZ_KERNEL_PATH", kernel_path.to_string_lossy().to_string())
-                    .env(
-                        "VZ_BASE_DISK_PATH",
-                        rootfs_path.to_string_lossy().to_string(),
-                    );
-            } else {
-                println!("No bundled VZ resources found, will download from registry");
+        let resources = vm::resolve_vm_resources(
+            &vm::PLATFORM,
+            app.path().resource_dir().ok(),
+            settings.vz_kernel_path.as_deref(),
+            settings.vz_base_disk_path.as_deref(),
+        )
+        .map_err(StartError::Setup)?;
+        for (name, path) in resources {
+            sidecar = sidecar.env(name, path.to_string_lossy().to_string());
+        }
+    }
+    // The server only runs VM sandboxes on macOS
+    #[cfg(not(target_os = "macos"))]
+    if settings.vz_kernel_path.is_some() || settings.vz_base_disk_path.is_some() {
+        warn!(
+            "VM sandboxes are only supported on macOS, ignoring the VM kernel and rootfs settings"
+        );
+    }
+
+    let extra_env = server_env::load();
+    if !extra_env.is_empty() {
+        let keys: Vec<&str> = extra_env.iter().map(|(key, _)| key.as_str()).collect();
+        info!("Applying extra server environment: {}", keys.join(", "));
+        sidecar = sidecar.envs(extra_env);
+    }
+
+    // Hand the bound ports over to the server as LISTEN_FD and SSH_LISTEN_FD.
+    // Where that isn't possible, release them just before spawning and let the
+    // server rebind them.
+    #[cfg(unix)]
+    let mut inherited = Vec::new();
+    #[cfg(unix)]
+    for (key, listener) in [("LISTEN_FD", listener), ("SSH_LISTEN_FD", ssh_listener)] {
+        let Some(listener) = listener else {
+            continue;
+        };
+        match sidecar::inheritable_fd(&listener) {
+            Ok(fd) => {
+                sidecar = sidecar.env(key, fd.to_string());
+                inherited.push(listener);
             }
+            Err(e) => warn!("{}, the server will bind its port itself", e),
         }
     }
+    #[cfg(not(unix))]
+    drop((listener, ssh_listener));
 
-    let (_rx, child) = sidecar
-        .spawn()
-        .map_err(|e| format!("Failed to spawn sidecar: {}", e))?;
+    // Output is read from the returned receiver and written to server.log by
+    // the supervisor, rather than having the server redirect it via LOG_FILE.
+    // Spawning can fail transiently, e.g. while antivirus software scans the
+    // binary, so it's retried; a spawned server that fails is the
+    // supervisor's business.
+    let attempts = settings
+        .spawn_attempts
+        .clamp(1, sidecar::MAX_SPAWN_ATTEMPTS);
+    let template: std::process::Command = sidecar.into();
+    let mut attempt = 1;
+    let (rx, child) = loop {
+        match sidecar::copy_command(app, &template, clear_env).spawn() {
+            Ok(spawned) => break spawned,
+            Err(e) if attempt < attempts => {
+                let message = format!(
+                    "Failed to spawn sidecar (attempt {} of {}): {}, retrying",
+                    attempt, attempts, e
+                );
+                warn!("{}", message);
+                logs::append_server_log(&message);
+                std::thread::sleep(Duration::from_millis(
+                    settings
+                        .spawn_retry_delay_ms
+                        .saturating_mul(u64::from(attempt))
+                        .min(sidecar::MAX_SPAWN_RETRY_DELAY_MS),
+                ));
+                attempt += 1;
+            }
+            Err(e) if attempt > 1 => {
+                return Err(sidecar_error(format!(
+                    "Failed to spawn sidecar after {} attempts: {}",
+                    attempt, e
+                )))
+            }
+            Err(e) => return Err(sidecar_error(format!("Failed to spawn sidecar: {}", e))),
+        }
+    };
+    // The sidecar has its own copies of the sockets now
+    #[cfg(unix)]
+    drop(inherited);
+    app.state::<Mutex<ServerState>>().lock().unwrap().env =
+        Some(server_env::ServerEnv::new(&template, !clear_env));
+
+    #[cfg(windows)]
+    if let Err(e) = process_tree::contain(child.pid()) {
+        warn!("Sidecar children may outlive the app: {}", e);
+    }
+
+    Ok((rx, child))
+}
+
+/// Stop the sidecar along with any helper processes it spawned. Waits up to
+/// 5s for them to exit, so never call it on the main thread.
+fn kill_sidecar(child: CommandChild) {
+    process_tree::kill_tree(child.pid());
+    let _ = child.kill();
+}
+
+const TRAY_ID: &str = "main";
+
+/// Record a new server status, reflect it in the tray tooltip and notify the
+/// frontend.
+fn set_server_status(app: &tauri::AppHandle, status: ServerStatus) {
+    let state = app.state::<Mutex<ServerState>>();
+    state.lock().unwrap().status = status.clone();
+    if let Some(tray) = app.tray_by_id(TRAY_ID) {
+        let tooltip = match status {
+            ServerStatus::Suspended => "Discobot (server suspended while idle)",
+            ServerStatus::Stopped => "Discobot (server stopped)",
+            ServerStatus::Failed { .. } => "Discobot (server couldn't start)",
+            _ => "Discobot",
+        };
+        let _ = tray.set_tooltip(Some(tooltip));
+    }
+    let _ = app.emit(SERVER_STATUS_EVENT, status);
+}
+
+/// Prepare logs and spawn the sidecar on a background task so window and tray
+/// creation in `setup` aren't held up by filesystem work.
+fn spawn_server_task(app: &tauri::AppHandle) {
+    app.state::<Mutex<ServerState>>()
+        .lock()
+        .unwrap()
+        .startup_times = startup_metrics::StartupTimes::begin();
+    let handle = app.clone();
+    let task = tauri::async_runtime::spawn_blocking(move || {
+        let (log_format, log_retention, flush_interval) = {
+            let settings = handle.state::<Mutex<Settings>>();
+            let settings = settings.lock().unwrap();
+            (
+                settings.log_format,
+                logs::LogRetention::from_settings(&settings),
+                Duration::from_millis(settings.log_flush_interval_ms),
+            )
+        };
+        let log = logs::ServerLog::open(log_format, log_retention, flush_interval);
+        // Show log file location
+        if let Ok(log_path) = logs::main_log_path() {
+            info!("Server logs will be written to: {}", log_path.display());
+        }
+
+        let state = handle.state::<Mutex<ServerState>>();
+        let (port, ssh_port, secret, listener, ssh_listener, socket) = {
+            let mut state = state.lock().unwrap();
+            (
+                state.port,
+                state.ssh_port,
+                state.secret.clone(),
+                state.listener.take(),
+                state.ssh_listener.take(),
+                state.socket.is_some(),
+            )
+        };
+        // After a restart the previous sidecar has just released the ports
+        let listener = listener
+            .or_else(|| TcpListener::bind(("127.0.0.1", port)).ok())
+            .filter(|_| !socket);
+        let ssh_listener = ssh_listener.or_else(|| TcpListener::bind(("127.0.0.1", ssh_port)).ok());
+
+        match start_server(
+            &handle,
+            port,
+            ssh_port,
+            secret.expose(),
+            listener,
+            ssh_listener,
+        ) {
+            Ok((rx, child)) => {
+                let alive = Arc::new(AtomicBool::new(true));
+                let generation = {
+                    let mut state = state.lock().unwrap();
+                    state.process = Some(child);
+                    state.alive = alive.clone();
+                    state.restart_required = false;
+                    state.generation += 1;
+                    state
+                        .startup_times
+                        .mark(startup_metrics::Milestone::Spawned);
+                    state.generation
+                };
+                // Stays `Starting` until the supervisor sees the ready line.
+                info!("Server spawned, waiting for it to report its port");
+                tauri::async_runtime::spawn(supervise_server(handle, rx, log, generation, alive));
+            }
+            Err(StartError::Sidecar {
+                path,
+                error,
+                missing,
+            }) => {
+                error!("Failed to start server at {}: {}", path.display(), error);
+                if missing {
+                    sidecar::report_missing(&handle);
+                }
+                sidecar::show_failure_dialog(&handle, &path, &error);
+                let error = format!("{} ({})", error, path.display());
+                state.lock().unwrap().error = Some(error.clone());
+                set_server_status(&handle, ServerStatus::Failed { error });
+            }
+            Err(StartError::Setup(error)) => {
+                notify(&handle, "Discobot server failed to start", &error);
+                state.lock().unwrap().error = Some(error.clone());
+                set_server_status(&handle, ServerStatus::Failed { error });
+            }
+        }
+    });
+
+    let state = app.state::<Mutex<ServerState>>();
+    state.lock().unwrap().startup = Some(task);
+}
+
+/// Write one line of sidecar output to server.log and, if enabled, queue it
+/// for the live log view.
+fn handle_output(
+    app: &tauri::AppHandle,
+    log: &mut logs::ServerLog,
+    batch: &mut logs::LogBatch,
+    stream: &'static str,
+    bytes: &[u8],
+) {
+    let text = String::from_utf8_lossy(bytes);
+    let line = text.trim_end_matches(['\r', '\n']);
+    let (stream, line) = match line.strip_prefix(CONTROL_REPLY_PREFIX) {
+        Some(reply) if stream == "stdout" => ("control", reply),
+        _ => (stream, line),
+    };
+    let line = app.state::<secret::Redactor>().redact(line);
+    log.write_line(stream, &line);
+    app.state::<logs::LogTail>()
+        .push(format!("[{}] {}", stream, line));
+
+    let forward_logs = app
+        .state::<Mutex<ServerState>>()
+        .lock()
+        .unwrap()
+        .forward_logs;
+    if forward_logs {
+        batch.push(app, stream, &line);
+    }
+}
+
+/// Whether this is the server's ready line for a Unix socket.
+fn parse_ready_socket(bytes: &[u8]) -> bool {
+    String::from_utf8_lossy(bytes).contains(SOCKET_LISTENING_MARKER)
+}
+
+/// Extract the bound port from the server's ready line, if this is it.
+fn parse_ready_port(bytes: &[u8]) -> Option<u16> {
+    let text = String::from_utf8_lossy(bytes);
+    let (_, rest) = text
+        .split_once(LISTENING_MARKER)
+        .or_else(|| text.split_once(READY_MARKER))?;
+    // Port 0 means "pick one", not where the server is listening
+    rest.trim_end().parse().ok().filter(|&port| port != 0)
+}
+
+/// Whether this is the server reporting it couldn't bind its port.
+fn parse_bind_failure(bytes: &[u8]) -> bool {
+    String::from_utf8_lossy(bytes).contains(BIND_FAILURE_MARKER)
+}
+
+/// Extract the SSH port from the server's SSH listening line, if this is it.
+fn parse_ssh_port(bytes: &[u8]) -> Option<u16> {
+    let text = String::from_utf8_lossy(bytes);
+    let (_, rest) = text.split_once(SSH_LISTENING_MARKER)?;
+    rest.trim_end().parse().ok().filter(|&port| port != 0)
+}
+
+/// Extract the address from the server's bind address line, if this is it.
+fn parse_bind_addr(bytes: &[u8]) -> Option<SocketAddr> {
+    let text = String::from_utf8_lossy(bytes);
+    let (_, rest) = text.split_once(BIND_ADDR_MARKER)?;
+    rest.trim_end().parse().ok()
+}
+
+/// Record the address the sidecar bound and warn if it isn't loopback.
+fn mark_bind_addr(app: &tauri::AppHandle, generation: u64, addr: SocketAddr) {
+    {
+        let state = app.state::<Mutex<ServerState>>();
+        let mut state = state.lock().unwrap();
+        if state.generation != generation || state.bind_addr == Some(addr) {
+            return;
+        }
+        state.bind_addr = Some(addr);
+    }
+    bind_info::check(app, addr);
+}
+
+/// Record the SSH port the sidecar reports, in case it had to bind its own,
+/// here and in the instance lock for launches that attach to it.
+fn mark_ssh_port(app: &tauri::AppHandle, generation: u64, port: u16) {
+    {
+        let state = app.state::<Mutex<ServerState>>();
+        let mut state = state.lock().unwrap();
+        if state.generation != generation || state.ssh_port == port {
+            return;
+        }
+        warn!(
+            "Server bound SSH port {} instead of {}",
+            port, state.ssh_port
+        );
+        state.ssh_port = port;
+    }
+    app.state::<instance_lock::InstanceLock>()
+        .update_ssh_port(port);
+}
 
-    // The server handles its own logging via LOG_FILE + dup2.
-    // We keep _rx alive (dropped when this function returns is fine since
-    // child is moved out), but don't need to process stdout/stderr.
+/// Record the port the sidecar actually bound and mark it running, telling
+/// the frontend to re-read the port if it differs from the one requested.
+fn mark_server_ready(
+    app: &tauri::AppHandle,
+    log: &mut logs::ServerLog,
+    generation: u64,
+    port: u16,
+) {
+    let (changed, ssh_port, socket) = {
+        let state = app.state::<Mutex<ServerState>>();
+        let mut state = state.lock().unwrap();
+        if state.generation != generation {
+            return;
+        }
+        let changed = state.port != port;
+        state.port = port;
+        state.startup_times.mark(startup_metrics::Milestone::Ready);
+        state.bind_attempts = 0;
+        state.error = None;
+        (changed, state.ssh_port, state.socket.clone())
+    };
 
-    Ok(child)
+    // Also in server.log so the ports can be found there
+    let started = match socket {
+        Some(socket) => format!(
+            "Server started on {}, SSH port {}",
+            socket.display(),
+            ssh_port
+        ),
+        None => format!("Server started on port {}, SSH port {}", port, ssh_port),
+    };
+    info!("{}", started);
+    log.write_line("discobot", &started);
+    remember_ports(app);
+    app.state::<instance_lock::InstanceLock>().update_port(port);
+    if changed {
+        warn!("Server bound port {} instead of the requested one", port);
+        let _ = app.emit(SERVER_PORT_EVENT, PortChanged { port });
+    }
+    set_server_status(app, ServerStatus::Running { port });
+    startup_metrics::probe_health(app, generation);
+    let _ = app.emit(SERVER_READY_EVENT, ());
+    app_ready::mark_server_ready(app);
+}
+
+/// Log a running sidecar's output, mark it running once it reports its port,
+/// and respawn it if it exits unexpectedly, giving up after
+/// `MAX_RESTART_ATTEMPTS` consecutive crashes. A sidecar that exits because it
+/// couldn't bind its port is respawned on a fresh one instead, up to
+/// `MAX_BIND_ATTEMPTS` times. A sidecar that never reports its port within
+/// `READY_TIMEOUT` is killed and startup reported as failed.
+async fn supervise_server(
+    app: tauri::AppHandle,
+    mut rx: tauri::async_runtime::Receiver<CommandEvent>,
+    mut log: logs::ServerLog,
+    generation: u64,
+    alive: Arc<AtomicBool>,
+) {
+    let started_at = Instant::now();
+    let ready_deadline = tokio::time::Instant::now() + READY_TIMEOUT;
+    let mut ready = false;
+    let mut seen_output = false;
+    let mut timed_out = false;
+    let mut bind_failed = false;
+    let mut batch = logs::LogBatch::default();
+    let mut exit = None;
+    loop {
+        let deadline = [
+            batch.deadline(),
+            log.deadline(),
+            (!ready).then_some(ready_deadline),
+        ]
+        .into_iter()
+        .flatten()
+        .min();
+        let next = match deadline {
+            Some(deadline) => match tokio::time::timeout_at(deadline, rx.recv()).await {
+                Ok(next) => next,
+                Err(_) => {
+                    let now = tokio::time::Instant::now();
+                    if !ready && now >= ready_deadline {
+                        timed_out = true;
+                        break;
+                    }
+                    if batch.deadline().is_some_and(|flush| flush <= now) {
+                        batch.flush(&app);
+                    }
+                    if log.deadline().is_some_and(|flush| flush <= now) {
+                        log.flush();
+                    }
+                    continue;
+                }
+            },
+            None => rx.recv().await,
+        };
+        let Some(event) = next else {
+            break;
+        };
+
+        let (stream, bytes) = match event {
+            CommandEvent::Stdout(bytes) => ("stdout", bytes),
+            CommandEvent::Stderr(bytes) => ("stderr", bytes),
+            // Flushed right away so crash context isn't lost
+            CommandEvent::Error(error) => {
+                log.write_line("error", &error);
+                log.flush();
+                continue;
+            }
+            CommandEvent::Terminated(payload) => {
+                exit = Some(payload);
+                break;
+            }
+            _ => continue,
+        };
+        if !seen_output {
+            seen_output = true;
+            startup_metrics::mark(&app, generation, startup_metrics::Milestone::FirstOutput);
+        }
+        if !ready {
+            if let Some(port) = parse_ready_port(&bytes) {
+                ready = true;
+                mark_server_ready(&app, &mut log, generation, port);
+            } else if parse_ready_socket(&bytes) {
+                ready = true;
+                mark_server_ready(&app, &mut log, generation, 0);
+            } else if parse_bind_failure(&bytes) {
+                bind_failed = true;
+            }
+        }
+        if let Some(port) = parse_ssh_port(&bytes) {
+            mark_ssh_port(&app, generation, port);
+        }
+        if let Some(addr) = parse_bind_addr(&bytes) {
+            mark_bind_addr(&app, generation, addr);
+        }
+        handle_output(&app, &mut log, &mut batch, stream, &bytes);
+    }
+    batch.flush(&app);
+    log.flush();
+    if !timed_out {
+        alive.store(false, Ordering::SeqCst);
+    }
+
+    let state = app.state::<Mutex<ServerState>>();
+    if timed_out {
+        let process = {
+            let mut state = state.lock().unwrap();
+            if state.shutting_down || state.generation != generation {
+                return;
+            }
+            state.env = None;
+            state.process.take()
+        };
+        if let Some(child) = process {
+            let _ = tauri::async_runtime::spawn_blocking(move || kill_sidecar(child)).await;
+        }
+        alive.store(false, Ordering::SeqCst);
+        let error = format!(
+            "Server did not report its port within {}s",
+            READY_TIMEOUT.as_secs()
+        );
+        notify(&app, "Discobot server failed to start", &error);
+        set_server_status(&app, ServerStatus::Failed { error });
+        return;
+    }
+
+    if bind_failed {
+        retry_on_new_port(&app, generation).await;
+        return;
+    }
+
+    let attempt = {
+        let mut state = state.lock().unwrap();
+        // A newer sidecar has replaced this one (manual restart) or we're quitting.
+        if state.shutting_down || state.generation != generation {
+            return;
+        }
+        state.env = None;
+        state.process = None;
+        if started_at.elapsed() >= RESTART_RESET_AFTER {
+            state.restart_attempts = 0;
+        }
+        state.restart_attempts += 1;
+        state.restart_attempts
+    };
+
+    let reason = match exit {
+        Some(payload) => match (payload.code, payload.signal) {
+            (Some(code), _) => format!("exit code {}", code),
+            (None, Some(signal)) => format!("signal {}", signal),
+            (None, None) => "unknown exit status".to_string(),
+        },
+        None => "lost contact with the process".to_string(),
+    };
+    warn!("Server exited unexpectedly ({})", reason);
+
+    if attempt > MAX_RESTART_ATTEMPTS {
+        let error = format!(
+            "Server crashed {} times in a row ({}), giving up",
+            MAX_RESTART_ATTEMPTS, reason
+        );
+        error!("{}", error);
+        notify(&app, "Discobot server stopped", &error);
+        set_server_status(&app, ServerStatus::Failed { error });
+        return;
+    }
+
+    notify(
+        &app,
+        "Discobot server crashed",
+        &format!(
+            "Restarting (attempt {} of {}) after {}",
+            attempt, MAX_RESTART_ATTEMPTS, reason
+        ),
+    );
+    restart_after(&app, generation, Duration::from_secs(u64::from(attempt))).await;
+}
+
+/// Bind a fresh port for a sidecar that exited because its port was taken,
+/// tell the frontend, and respawn it there. Gives up after
+/// `MAX_BIND_ATTEMPTS` in a row.
+async fn retry_on_new_port(app: &tauri::AppHandle, generation: u64) {
+    let state = app.state::<Mutex<ServerState>>();
+    let rebound = {
+        let mut state = state.lock().unwrap();
+        if state.shutting_down || state.generation != generation {
+            return;
+        }
+        state.env = None;
+        state.process = None;
+        state.bind_attempts += 1;
+        if state.bind_attempts > MAX_BIND_ATTEMPTS {
+            None
+        } else {
+            ports::bind(std::iter::empty(), state.port_range).map(|listener| {
+                let old_port = state.port;
+                state.port = listener.local_addr().map_or(0, |addr| addr.port());
+                state.listener = Some(listener);
+                (old_port, state.port, state.bind_attempts)
+            })
+        }
+    };
+
+    let Some((old_port, port, attempt)) = rebound else {
+        let error = format!(
+            "Server couldn't bind a port after {} attempts",
+            MAX_BIND_ATTEMPTS
+        );
+        error!("{}", error);
+        notify(app, "Discobot server failed to start", &error);
+        state.lock().unwrap().error = Some(error.clone());
+        set_server_status(app, ServerStatus::Failed { error });
+        return;
+    };
+    warn!(
+        "Port {} was taken, retrying on port {} (attempt {} of {})",
+        old_port, port, attempt, MAX_BIND_ATTEMPTS
+    );
+    let _ = app.emit(SERVER_PORT_EVENT, PortChanged { port });
+    restart_after(app, generation, Duration::ZERO).await;
+}
+
+/// Spawn a new sidecar after `delay`, unless the server was stopped or
+/// restarted by hand in the meantime.
+async fn restart_after(app: &tauri::AppHandle, generation: u64, delay: Duration) {
+    set_server_status(app, ServerStatus::Starting);
+    tokio::time::sleep(delay).await;
+    {
+        let state = app.state::<Mutex<ServerState>>();
+        let state = state.lock().unwrap();
+        // Quitting during the backoff mustn't start a sidecar that outlives the app
+        if state.shutting_down || state.generation != generation {
+            return;
+        }
+    }
+    spawn_server_task(app);
+}
+
+/// Kill the current sidecar (if any) and spawn a fresh one on the same port
+/// and secret, so the frontend doesn't need to re-handshake.
+fn respawn_server(app: &tauri::AppHandle) {
+    let state = app.state::<Mutex<ServerState>>();
+    if state.lock().unwrap().external {
+        return;
+    }
+    let startup = state.lock().unwrap().startup.take();
+    if let Some(task) = startup {
+        let _ = tauri::async_runtime::block_on(task);
+    }
+
+    let process = {
+        let mut state = state.lock().unwrap();
+        if state.shutting_down {
+            return;
+        }
+        // Invalidate the running supervisor so the kill isn't treated as a crash.
+        state.generation += 1;
+        state.restart_attempts = 0;
+        state.bind_attempts = 0;
+        state.suspended = false;
+        state.env = None;
+        state.process.take()
+    };
+    if let Some(child) = process {
+        kill_sidecar(child);
+    }
+
+    set_server_status(app, ServerStatus::Starting);
+    spawn_server_task(app);
+}
+
+/// Note that the running sidecar needs a restart to pick up a setting change
+/// and tell the frontend, then restart it once settings have stopped changing
+/// for `SETTINGS_RESTART_DEBOUNCE`. While agent tasks are running, or the
+/// server can't say, it's left to the frontend's prompt and `restart_server`
+/// so no work is lost.
+fn mark_restart_required(app: &tauri::AppHandle) {
+    let change = {
+        let state = app.state::<Mutex<ServerState>>();
+        let mut state = state.lock().unwrap();
+        // A server that isn't running reads the new setting when it starts
+        if state.external || state.process.is_none() {
+            return;
+        }
+        state.restart_required = true;
+        state.settings_changes += 1;
+        state.settings_changes
+    };
+    let _ = app.emit(SERVER_RESTART_REQUIRED_EVENT, ());
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(SETTINGS_RESTART_DEBOUNCE).await;
+        let (endpoint, secret) = {
+            let state = app.state::<Mutex<ServerState>>();
+            let state = state.lock().unwrap();
+            // Superseded by a later change, or restarted some other way
+            if state.settings_changes != change || !state.restart_required {
+                return;
+            }
+            (state.endpoint(), state.secret.clone())
+        };
+        if health::active_tasks(&endpoint, secret.expose()).await != Some(0) {
+            info!("Not restarting server for changed settings while tasks may be running");
+            return;
+        }
+        {
+            let state = app.state::<Mutex<ServerState>>();
+            let state = state.lock().unwrap();
+            if state.settings_changes != change || !state.restart_required {
+                return;
+            }
+        }
+        info!("Restarting server to apply changed settings");
+        let _ = app.emit(SERVER_RESTARTING_EVENT, ());
+        let _ = tauri::async_runtime::spawn_blocking(move || respawn_server(&app)).await;
+    });
+}
+
+#[tauri::command]
+fn restart_server(app: tauri::AppHandle) -> Result<(), String> {
+    let state = app.state::<Mutex<ServerState>>();
+    if state.lock().unwrap().external {
+        return Err("The server is managed externally".to_string());
+    }
+    info!("Restarting server on request");
+    logs::append_server_log("Restarting server on request");
+    tauri::async_runtime::spawn_blocking(move || respawn_server(&app));
+    Ok(())
+}
+
+/// Stop the sidecar, e.g. to save battery, until `start_server_cmd`. Its
+/// ports stay reserved and the secret unchanged, so the frontend reconnects
+/// without a new handshake.
+fn stop_server(app: &tauri::AppHandle) {
+    let state = app.state::<Mutex<ServerState>>();
+    let startup = state.lock().unwrap().startup.take();
+    if let Some(task) = startup {
+        let _ = tauri::async_runtime::block_on(task);
+    }
+
+    let process = {
+        let mut state = state.lock().unwrap();
+        if state.shutting_down || matches!(state.status, ServerStatus::Stopped) {
+            return;
+        }
+        // Invalidate the running supervisor so the kill isn't treated as a crash.
+        state.generation += 1;
+        state.suspended = false;
+        state.restart_required = false;
+        state.alive.store(false, Ordering::SeqCst);
+        state.env = None;
+        state.process.take()
+    };
+    if let Some(child) = process {
+        kill_sidecar(child);
+    }
+
+    {
+        let mut state = state.lock().unwrap();
+        let (port, ssh_port) = (state.port, state.ssh_port);
+        if state.socket.is_none() {
+            state.listener = TcpListener::bind(("127.0.0.1", port)).ok();
+        }
+        state.ssh_listener = TcpListener::bind(("127.0.0.1", ssh_port)).ok();
+    }
+    info!("Server stopped");
+    logs::append_server_log("Server stopped");
+    set_server_status(app, ServerStatus::Stopped);
+}
+
+#[tauri::command]
+async fn stop_server_cmd(app: tauri::AppHandle) -> Result<(), String> {
+    let state = app.state::<Mutex<ServerState>>();
+    if state.lock().unwrap().external {
+        return Err("The server is managed externally".to_string());
+    }
+    tauri::async_runtime::spawn_blocking(move || stop_server(&app))
+        .await
+        .map_err(|e| format!("Failed to stop server: {}", e))
+}
+
+/// Start the sidecar again after `stop_server_cmd`, a failure or an idle
+/// suspend, on the same ports and secret.
+#[tauri::command]
+fn start_server_cmd(app: tauri::AppHandle) -> Result<(), String> {
+    {
+        let state = app.state::<Mutex<ServerState>>();
+        let mut state = state.lock().unwrap();
+        if state.external {
+            return Err("The server is managed externally".to_string());
+        }
+        if state.shutting_down {
+            return Err("The app is quitting".to_string());
+        }
+        // Checked under the same lock that marks it starting, so two calls
+        // can't both spawn
+        if state.alive.load(Ordering::SeqCst)
+            || matches!(
+                state.status,
+                ServerStatus::Starting | ServerStatus::Running { .. }
+            )
+        {
+            return Err("The server is already running".to_string());
+        }
+        state.status = ServerStatus::Starting;
+        state.suspended = false;
+        state.restart_attempts = 0;
+    }
+
+    info!("Starting server");
+    logs::append_server_log("Starting server");
+    set_server_status(&app, ServerStatus::Starting);
+    spawn_server_task(&app);
+    Ok(())
+}
+
+/// Stop the sidecar before exiting, waiting for an in-flight spawn to finish
+/// first so a child spawned during shutdown isn't left behind. Blocks, so
+/// the quit paths run it with `spawn_blocking` and keep the UI responsive.
+fn shutdown_server(app: &tauri::AppHandle) {
+    let state = app.state::<Mutex<ServerState>>();
+    let startup = {
+        let mut state = state.lock().unwrap();
+        state.shutting_down = true;
+        // Invalidates the supervisor and any pending restart
+        state.generation += 1;
+        state.startup.take()
+    };
+    if let Some(task) = startup {
+        let _ = tauri::async_runtime::block_on(task);
+    }
+    let process = state.lock().unwrap().process.take();
+    if let Some(child) = process {
+        kill_sidecar(child);
+    }
+    app.state::<instance_lock::InstanceLock>().release();
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    // In dev mode, use fixed ports and no secret (server runs separately).
+    shell_log::init();
+    let settings = settings::load();
+    shell_log::configure(&settings);
+    logs::init_log_dir(settings.log_dir.as_deref());
+    logs::set_split_streams(settings.split_log_streams);
+    logs::set_format(settings.log_format);
+    logs::tidy_runs(logs::LogRetention::from_settings(&settings));
+    structured_logs::configure(&settings);
+    let launch_args = std::env::args().collect::<Vec<_>>();
+    let headless = args::is_headless(&launch_args);
+    let start_hidden = settings.start_hidden || args::is_hidden(&launch_args);
+
+    // Dev builds attach to the server started separately via `pnpm dev:api`,
+    // unless DISCOBOT_SPAWN_SIDECAR=1 asks for the sidecar on the dev ports.
+    // Otherwise DISCOBOT_EXTERNAL_SERVER (or the matching setting) attaches
+    // to an already running server instead of spawning the sidecar.
+    let dev_server = cfg!(debug_assertions) && !spawn_sidecar_in_dev();
+    let external = if dev_server {
+        Ok(Some(external::ExternalServer {
+            host: "127.0.0.1".to_string(),
+            port: DEV_PORT,
+            secret: String::new(),
+        }))
+    } else {
+        external::resolve(settings.external_server.as_deref())
+    };
+
+    // Only a sidecar of our own touches the data directory
+    let mut held_by = None;
+    let instance_lock = match &external {
+        Ok(None) => match instance_lock::acquire() {
+            Ok(lock) => lock,
+            Err(instance_lock::LockError::Held(holder)) => {
+                held_by = Some(holder);
+                instance_lock::InstanceLock::default()
+            }
+            Err(instance_lock::LockError::Io(e)) => {
+                warn!("{}, running without an instance lock", e);
+                instance_lock::InstanceLock::default()
+            }
+        },
+        _ => instance_lock::InstanceLock::default(),
+    };
+    let external = match &held_by {
+        Some(holder) if headless => {
+            error!("{}", holder.describe());
+            std::process::exit(1);
+        }
+        Some(holder) => Err(holder.describe()),
+        None => external,
+    };
+
+    // In dev mode, use fixed ports and no secret.
     // In release mode, find available ports and generate a shared secret.
-    #[cfg(debug_assertions)]
-    let (port, secret) = (3001_u16, String::new());
+    let (host, port, ssh_port, secret, bound) = match &external {
+        // The SSH port isn't discoverable, so assume the server's default
+        Ok(Some(server)) => (
+            server.host.clone(),
+            server.port,
+            DEFAULT_SSH_PORT,
+            Secret::from(server.secret.clone()),
+            BoundPorts::default(),
+        ),
+        _ if cfg!(debug_assertions) => (
+            "127.0.0.1".to_string(),
+            DEV_PORT,
+            DEFAULT_SSH_PORT,
+            Secret::default(),
+            BoundPorts::default(),
+        ),
+        _ => {
+            let last = last_ports::load();
+            let range = ports::range(settings.port_range);
+            let ssh_listener = bind_ssh_port(last.map(|last| last.ssh_port), range);
+            let ssh = match &ssh_listener {
+                Some(listener) => listener
+                    .local_addr()
+                    .expect("Failed to get local address")
+                    .port(),
+                None => {
+                    warn!(
+                        "Failed to bind an SSH port, the server will try {} itself",
+                        DEFAULT_SSH_PORT
+                    );
+                    DEFAULT_SSH_PORT
+                }
+            };
+            // Outside the range the default isn't expected to be used
+            let busy_ssh_port = Some(DEFAULT_SSH_PORT).filter(|&default| {
+                default != ssh && range.is_none_or(|range| range.contains(default))
+            });
+            if let Some(busy) = busy_ssh_port {
+                warn!("SSH port {} was busy, using {}", busy, ssh);
+            }
+            let secret = secret_store::launch_secret(settings.persist_secret);
+            let bound = BoundPorts {
+                ssh_listener,
+                busy_ssh_port,
+                port_range: range,
+                ..BoundPorts::default()
+            };
+            // Serving on a socket needs no TCP port at all
+            if let Some(socket) = server_socket::resolve(settings.unix_socket) {
+                info!("Serving the API on {}", socket.display());
+                let bound = BoundPorts {
+                    socket: Some(socket),
+                    ..bound
+                };
+                ("127.0.0.1".to_string(), 0, ssh, secret, bound)
+            } else {
+                // A fixed port wins over the one remembered from the last
+                // launch, which is only reused if it's within the range
+                let fixed = fixed_port(settings.fixed_port);
+                let last_port = last
+                    .map(|last| last.port)
+                    .filter(|&port| range.is_none_or(|range| range.contains(port)));
+                let listener = bind_server_port(fixed.or(last_port), range);
+                let port = listener
+                    .local_addr()
+                    .expect("Failed to get local address")
+                    .port();
+                let busy_port = fixed.filter(|&fixed| fixed != port);
+                if let Some(busy) = busy_port {
+                    warn!("Preferred port {} was busy, using {}", busy, port);
+                }
+                let bound = BoundPorts {
+                    listener: Some(listener),
+                    busy_port,
+                    ..bound
+                };
+                ("127.0.0.1".to_string(), port, ssh, secret, bound)
+            }
+        }
+    };
 
-    #[cfg(not(debug_assertions))]
-    let (port, ssh_port, secret) = {
-        let ssh = if TcpListener::bind("127.0.0.1:3333").is_ok() {
-            3333
-        } else {
-            find_available_port()
-        };
-        (find_available_port(), ssh, generate_secret())
+    let using = match &bound.socket {
+        Some(socket) => format!("Using {} and SSH port {}", socket.display(), ssh_port),
+        None => format!("Using {}:{} and SSH port {}", host, port, ssh_port),
+    };
+    info!("{}", using);
+    logs::append_server_log(&using);
+    instance_lock.record(instance_lock::LockInfo {
+        pid: std::process::id(),
+        port,
+        ssh_port,
+        socket: bound.socket.clone(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        secret: secret.expose().to_string(),
+    });
+
+    let (status, error) = match &external {
+        Ok(Some(_)) => (ServerStatus::External, None),
+        Ok(None) => (ServerStatus::Starting, None),
+        Err(error) => (
+            ServerStatus::Failed {
+                error: error.clone(),
+            },
+            Some(error.clone()),
+        ),
     };
 
+    let redactor = secret::Redactor::default();
+    redactor.add(&secret);
+
     tauri::Builder::default()
+        .register_asynchronous_uri_scheme_protocol(api_proxy::SCHEME, api_proxy::handle_request)
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_os::init())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_process::init())
+        .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
-        .plugin(tauri_plugin_single_instance::init(|app, _args, _cwd| {
+        // Must be registered first; with its `deep-link` feature it hands
+        // URLs from a second launch to the deep-link plugin in this instance.
+        .plugin(tauri_plugin_single_instance::init(|app, argv, cwd| {
             show_window(app);
+            let targets = args::parse_open_targets(&argv, &cwd);
+            if !targets.is_empty() {
+                let _ = app.emit(
+                    args::SECOND_INSTANCE_EVENT,
+                    args::SecondInstanceArgs { targets, cwd },
+                );
+            }
         }))
+        .plugin(tauri_plugin_deep_link::init())
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
         .plugin(
             tauri_plugin_window_state::Builder::new()
                 .with_state_flags(window_state_flags())
                 .build(),
         )
         .manage(Mutex::new(ServerState {
+            host,
             port,
-            secret: secret.clone(),
-            #[cfg(not(debug_assertions))]
+            socket: bound.socket,
+            bind_addr: None,
+            busy_port: bound.busy_port,
+            port_range: bound.port_range,
+            ssh_port,
+            busy_ssh_port: bound.busy_ssh_port,
+            secret,
+            status,
+            listener: bound.listener,
+            ssh_listener: bound.ssh_listener,
             process: None,
+            alive: Arc::new(AtomicBool::new(false)),
+            startup: None,
+            restart_attempts: 0,
+            bind_attempts: 0,
+            shutting_down: false,
+            error,
+            forward_logs: false,
+            external: matches!(external, Ok(Some(_))),
+            suspended: false,
+            restart_required: false,
+            settings_changes: 0,
+            generation: 0,
+            startup_times: startup_metrics::StartupTimes::default(),
+            env: None,
         }))
+        .manage(Mutex::new(settings))
+        .manage(first_run::FirstRun::detect())
+        .manage(app_ready::AppReady::default())
+        .manage(instance_lock)
+        .manage(secret_access::SecretAccess::default())
+        .manage(redactor)
+        .manage(logs::LogTail::default())
+        .on_page_load(|webview, payload| {
+            webview
+                .state::<first_run::FirstRun>()
+                .on_page_load(webview, payload);
+            webview
+                .state::<secret_access::SecretAccess>()
+                .on_page_load(webview, payload);
+            webview
+                .state::<logs::LogTail>()
+                .on_page_load(webview, payload);
+        })
         .setup(move |app| {
+            // The main window isn't created from the config automatically so
+            // headless launches never have one.
+            if headless {
+                info!("Running headless, press Ctrl-C to quit");
+            } else if let Some(config) = app.config().app.windows.first() {
+                // The window-state plugin restores geometry and visibility
+                // as the window is built
+                let window = WebviewWindowBuilder::from_config(app.handle(), config)?.build()?;
+                ensure_on_screen(&window);
+                // Bring it to the front so onboarding is seen
+                if app.state::<first_run::FirstRun>().is_first_run() {
+                    show_window(app.handle());
+                } else if start_hidden {
+                    hide_window(app.handle());
+                } else if window.is_visible().unwrap_or(false) {
+                    app_ready::mark_window_shown(app.handle());
+                }
+                if let Some(holder) = held_by {
+                    holder.show_dialog(app.handle());
+                }
+            }
+
             // On macOS, set activation policy based on window visibility
             #[cfg(target_os = "macos")]
             {
@@ -241,6 +2082,7 @@ pub fn run() {
                 if let Some(window) = app.get_webview_window("main") {
                     if window.is_visible().unwrap_or(false) {
                         let _ = app.set_activation_policy(ActivationPolicy::Regular);
+                        dock::install_menu(app.handle());
                     } else {
                         let _ = app.set_activation_policy(ActivationPolicy::Accessory);
                     }
@@ -249,47 +2091,102 @@ pub fn run() {
                 }
             }
 
-            // Only start the Go server in release mode
-            // In dev mode, run it separately via `pnpm dev:api`
-            #[cfg(not(debug_assertions))]
-            {
-                // Show log file location
-                if let Ok(log_path) = get_log_file_path() {
-                    println!("Server logs will be written to: {}", log_path.display());
+            // Installed bundles register the scheme themselves; AppImages and
+            // dev builds need it done at runtime.
+            #[cfg(any(target_os = "linux", all(debug_assertions, windows)))]
+            if let Err(e) = app.deep_link().register_all() {
+                warn!("Failed to register deep link schemes: {}", e);
+            }
+
+            // A URL that launched the app is picked up by the frontend via the
+            // plugin's `getCurrent()`; later ones arrive through this handler.
+            let handle = app.handle().clone();
+            app.deep_link().on_open_url(move |event| {
+                show_window(&handle);
+                for url in event.urls() {
+                    let _ = handle.emit(
+                        OPEN_DEEP_LINK_EVENT,
+                        DeepLink {
+                            url: url.to_string(),
+                        },
+                    );
                 }
+            });
 
-                match start_server(app.handle(), port, ssh_port, &secret) {
-                    Ok(child) => {
-                        let state = app.state::<Mutex<ServerState>>();
-                        state.lock().unwrap().process = Some(child);
-                        println!("Server started on port {}", port);
+            // The dev server is restarted by hand, so it isn't monitored
+            if !dev_server {
+                match &external {
+                    Ok(None) => {
+                        spawn_server_task(app.handle());
+                        if !headless {
+                            idle::spawn_idle_monitor(app.handle().clone());
+                        }
                     }
-                    Err(e) => {
-                        eprintln!("Failed to start server: {}", e);
+                    Ok(Some(server)) => {
+                        info!(
+                            "Attaching to external server at {}:{}",
+                            server.host, server.port
+                        );
+                        let endpoint = health::Endpoint::tcp(&server.host, server.port);
+                        let (host, port) = (server.host.clone(), server.port);
+                        let handle = app.handle().clone();
+                        tauri::async_runtime::spawn(async move {
+                            if health::check_health(&endpoint).await {
+                                app_ready::mark_server_ready(&handle);
+                            } else {
+                                warn!("External server at {}:{} is not responding", host, port);
+                            }
+                        });
                     }
+                    Err(error) => error!("Not starting server: {}", error),
                 }
+                wake::spawn_wake_monitor(app.handle().clone());
+                watchdog::spawn_watchdog(app.handle().clone());
+                network::spawn_network_monitor(app.handle().clone());
+            } else {
+                // Nothing to wait for; the frontend finds out itself if the
+                // dev server isn't up
+                app_ready::mark_server_ready(app.handle());
+            }
+
+            signals::spawn_signal_handler(app.handle().clone());
+            if headless {
+                return Ok(());
             }
 
             // Create tray menu
             let show_item = MenuItem::with_id(app, "show", "Show", true, None::<&str>)?;
+            // Dev builds log the shell's own events even when attached to
+            // the dev server
+            let logs_item = MenuItem::with_id(app, "logs", "View Logs", true, None::<&str>)?;
+            let log_folder_item =
+                MenuItem::with_id(app, "log_folder", "Open Logs Folder", true, None::<&str>)?;
             let quit_item = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
-            let menu = Menu::with_items(app, &[&show_item, &quit_item])?;
+            let menu =
+                Menu::with_items(app, &[&show_item, &logs_item, &log_folder_item, &quit_item])?;
 
-            // Create tray icon with monochrome template image for macOS light/dark mode
-            let tray_icon = tauri::image::Image::from_bytes(include_bytes!(
-                "../icons/tray-icon@2x.png"
-            ))?;
-            TrayIconBuilder::new()
+            // Create tray icon with monochrome template image for macOS
+            // light/dark mode, and a variant for the theme elsewhere
+            let tray_icon = tray_theme::icon(tray_theme::current(app.handle()))?;
+            let tray = TrayIconBuilder::with_id(TRAY_ID)
                 .icon(tray_icon)
+                .tooltip("Discobot")
                 .icon_as_template(true)
                 .menu(&menu)
                 .show_menu_on_left_click(false)
-                .on_menu_event(|app, event| match event.id.as_ref() {
-                    "show" => show_window(app),
-                    "quit" => {
-                        app.exit(0);
+                .on_menu_event(|app, event| {
+                    debug!("Tray menu item '{}' selected", event.id.as_ref());
+                    match event.id.as_ref() {
+                        "show" => show_window(app),
+                        "logs" => logs::open_log_file(app),
+                        "log_folder" => {
+                            if let Err(e) = logs::open_log_folder(app.clone()) {
+                                warn!("{}", e);
+                            }
+                        }
+                        "quit" => quit::request_tray_quit(app),
+                        _ => {}
                     }
-                    _ => {}
                 })
                 .on_tray_icon_event(|tray, event| {
                     if let TrayIconEvent::Click {
@@ -298,20 +2195,128 @@ pub fn run() {
                         ..
                     } = event
                     {
+                        debug!("Tray icon clicked");
                         toggle_window(tray.app_handle());
                     }
                 })
                 .build(app)?;
+            let show_tray = app
+                .state::<Mutex<Settings>>()
+                .lock()
+                .unwrap()
+                .show_tray_icon;
+            if !show_tray {
+                if main_window_visible(app.handle()) {
+                    tray.set_visible(false)?;
+                } else {
+                    warn!("Showing the tray icon since the window starts hidden");
+                }
+            }
 
             Ok(())
         })
-        .on_window_event(|window, event| {
-            if let WindowEvent::CloseRequested { api, .. } = event {
-                hide_window(window.app_handle());
+        .on_window_event(|window, event| match event {
+            WindowEvent::CloseRequested { api, .. } => {
                 api.prevent_close();
+                let app = window.app_handle();
+                let behavior = app
+                    .state::<Mutex<Settings>>()
+                    .lock()
+                    .unwrap()
+                    .close_behavior;
+                match behavior {
+                    CloseBehavior::Hide => hide_window(app),
+                    CloseBehavior::Quit => quit::request_quit(app),
+                    CloseBehavior::Ask => {
+                        let _ = app.emit(CLOSE_REQUESTED_EVENT, ());
+                    }
+                }
+            }
+            WindowEvent::Focused(_) if window.label() == "main" => {
+                let visible = window.is_visible().unwrap_or(false);
+                emit_window_visibility(window.app_handle(), visible);
             }
+            WindowEvent::ThemeChanged(theme) if window.label() == "main" => {
+                tray_theme::apply(window.app_handle(), *theme);
+            }
+            WindowEvent::Destroyed => {
+                window
+                    .state::<logs::LogTail>()
+                    .remove_webview(window.label());
+            }
+            _ => {}
         })
-        .invoke_handler(tauri::generate_handler![get_server_port, get_server_secret, save_file_to_downloads])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .invoke_handler(shell_log::log_commands(tauri::generate_handler![
+            get_server_port,
+            get_server_port_info,
+            get_server_url,
+            get_ssh_port,
+            get_ssh_command,
+            reset_window_state,
+            server_socket::get_server_socket,
+            api_proxy::get_api_proxy_url,
+            is_server_running,
+            secret_access::get_server_secret,
+            secret_store::rotate_secret,
+            user_secrets::secret_store_set,
+            user_secrets::secret_store_get,
+            user_secrets::secret_store_delete,
+            user_secrets::secret_store_list,
+            get_server_status,
+            get_server_error,
+            get_server_info,
+            get_versions,
+            app_ready::is_app_ready,
+            bind_info::get_server_bind_info,
+            restart_server,
+            start_server_cmd,
+            stop_server_cmd,
+            set_server_log_forwarding,
+            send_server_command,
+            server_control,
+            set_server_log_level,
+            get_server_log_level,
+            is_window_visible,
+            show_main_window,
+            hide_main_window,
+            quit_app,
+            save_file_to_downloads,
+            settings::get_settings,
+            settings::update_settings,
+            last_ports::clear_preferred_port,
+            settings::set_fixed_port,
+            server_env::get_effective_server_env,
+            server_env::get_server_env,
+            logs::clear_server_log,
+            logs::truncate_logs_now,
+            startup_metrics::get_startup_metrics,
+            logs::get_log_config,
+            logs::get_server_logs,
+            logs::list_log_runs,
+            app_dirs::open_data_dir,
+            app_dirs::open_config_dir,
+            logs::tail_server_logs,
+            logs::untail_server_logs,
+            logs::open_log_folder,
+            set_tray_visible,
+            get_tray_visible,
+            structured_logs::set_log_file_level,
+            structured_logs::get_log_file_level,
+            is_dev_build,
+            orphans::cleanup_orphans,
+            locale::get_system_locale,
+            first_run::is_first_run,
+            features::get_feature_flags,
+            features::set_feature_flag,
+            resources::get_host_resources,
+            resources::get_vm_limits,
+            resources::set_vm_limits
+        ]))
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let RunEvent::ExitRequested { api, code, .. } = event {
+                quit::on_exit_requested(app, &api, code);
+            }
+        });
 }
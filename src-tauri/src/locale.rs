@@ -0,0 +1,93 @@
+use std::sync::Mutex;
+
+use log::warn;
+use serde::Serialize;
+
+use crate::settings::Settings;
+
+const FALLBACK_LOCALE: &str = "en-US";
+const FALLBACK_TIMEZONE: &str = "UTC";
+
+/// The user's language and timezone, for the server and the frontend's i18n.
+#[derive(Clone, Serialize)]
+pub struct SystemLocale {
+    /// BCP 47 tag such as `en-US`.
+    pub locale: String,
+    /// IANA name such as `Europe/Berlin`.
+    pub timezone: String,
+    /// Whether `locale` comes from `Settings::language` rather than the OS.
+    pub overridden: bool,
+}
+
+/// Detect the OS locale and timezone, falling back to en-US and UTC. A
+/// `language` set in settings takes precedence over the OS locale.
+pub fn resolve(language: Option<&str>) -> SystemLocale {
+    let language = language.filter(|language| !language.is_empty());
+    let locale = language
+        .map(str::to_string)
+        .or_else(sys_locale::get_locale)
+        .unwrap_or_else(|| FALLBACK_LOCALE.to_string());
+    let timezone = iana_time_zone::get_timezone().unwrap_or_else(|e| {
+        warn!("Could not detect timezone, using UTC: {}", e);
+        FALLBACK_TIMEZONE.to_string()
+    });
+
+    SystemLocale {
+        locale,
+        timezone,
+        overridden: language.is_some(),
+    }
+}
+
+/// Environment variables giving the sidecar the user's locale, which a GUI
+/// launch doesn't otherwise provide.
+pub fn env(locale: &SystemLocale) -> Vec<(&'static str, String)> {
+    vec![
+        ("DISCOBOT_LOCALE", locale.locale.clone()),
+        ("LANG", format!("{}.UTF-8", posix_locale(&locale.locale))),
+        ("TZ", locale.timezone.clone()),
+    ]
+}
+
+/// POSIX wants `en_US` where BCP 47 has `en-US`. It has no place for script
+/// subtags or extensions, so `zh-Hant-TW` becomes `zh_TW`.
+fn posix_locale(tag: &str) -> String {
+    let mut subtags = tag.split(['-', '_']).filter(|subtag| !subtag.is_empty());
+    let language = subtags.next().unwrap_or("en").to_ascii_lowercase();
+    let is_script =
+        |subtag: &str| subtag.len() == 4 && subtag.chars().all(|c| c.is_ascii_alphabetic());
+    let region = subtags.find(|subtag| !is_script(subtag)).filter(|subtag| {
+        (subtag.len() == 2 && subtag.chars().all(|c| c.is_ascii_alphabetic()))
+            || (subtag.len() == 3 && subtag.chars().all(|c| c.is_ascii_digit()))
+    });
+    match region {
+        Some(region) => format!("{}_{}", language, region.to_ascii_uppercase()),
+        None => language,
+    }
+}
+
+#[tauri::command]
+pub fn get_system_locale(settings: tauri::State<'_, Mutex<Settings>>) -> SystemLocale {
+    resolve(settings.lock().unwrap().language.as_deref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn posix_locale_keeps_language_and_region() {
+        assert_eq!(posix_locale("en-US"), "en_US");
+        assert_eq!(posix_locale("de"), "de");
+        assert_eq!(posix_locale("pt_br"), "pt_BR");
+        assert_eq!(posix_locale("es-419"), "es_419");
+    }
+
+    #[test]
+    fn posix_locale_drops_script_and_extensions() {
+        assert_eq!(posix_locale("zh-Hant-TW"), "zh_TW");
+        assert_eq!(posix_locale("sr-Latn"), "sr");
+        assert_eq!(posix_locale("en-US-u-ca-gregory"), "en_US");
+        assert_eq!(posix_locale("de-u-co-phonebk"), "de");
+    }
+}
@@ -0,0 +1,1369 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use tauri::ipc::Channel;
+use tauri::webview::{PageLoadEvent, PageLoadPayload};
+use tauri::{Emitter, Webview};
+use tauri_plugin_opener::OpenerExt;
+
+use crate::settings::{LogFormat, Settings};
+use crate::{shell_log, structured_logs};
+
+/// Keep the logs of this many runs, counting the current one...
+pub const DEFAULT_RETENTION_RUNS: u32 = 10;
+/// ...as long as together they take up no more than this.
+pub const DEFAULT_RETENTION_BYTES: u64 = 50 * 1024 * 1024;
+
+/// Default for `Settings::log_flush_interval_ms`.
+pub const DEFAULT_FLUSH_INTERVAL_MS: u64 = 500;
+/// Buffered lines are written out early once there are this many.
+const MAX_BUFFERED_LINES: usize = 500;
+
+/// Most lines `get_server_logs` returns.
+const MAX_TAIL_LINES: usize = 5000;
+/// How much of a log file `get_server_logs` reads at a time, from the end.
+const TAIL_CHUNK_BYTES: u64 = 64 * 1024;
+/// Reads restarted because the file shrank or moved underneath, before
+/// giving up.
+const TAIL_ATTEMPTS: u32 = 3;
+
+/// Emitted with batches of sidecar output while log forwarding is enabled.
+const SERVER_LOG_EVENT: &str = "server-log";
+
+/// Lines arriving within this window are delivered in a single event.
+const BATCH_WINDOW: Duration = Duration::from_millis(100);
+/// Upper bound on lines per event so one burst can't produce a huge payload.
+const MAX_BATCH_LINES: usize = 200;
+
+/// Recent lines sent to a `tail_server_logs` subscriber before live output.
+const TAIL_BACKLOG_LINES: usize = 500;
+
+/// Each run logs to `server-<run>.log`, or with `Settings::split_log_streams`
+/// to `server-<run>.out.log` and `server-<run>.err.log`.
+const LOG_PREFIX: &str = "server-";
+const LOG_EXTENSION: &str = ".log";
+const STDOUT_SUFFIX: &str = ".out";
+const STDERR_SUFFIX: &str = ".err";
+const GZ_EXTENSION: &str = ".gz";
+
+/// `server-latest.log` links to the current run's file.
+const LATEST_RUN: &str = "latest";
+
+/// Run ids are the local time the run started, which keeps them sorted.
+const RUN_ID_FORMAT: &str = "%Y%m%d-%H%M%S-%3f";
+/// Timestamps in the text log format.
+const TEXT_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+const MAX_RUN_ID_LENGTH: usize = 32;
+
+/// Overrides `Settings::log_dir`, and tells the server where its logs are.
+pub const LOG_DIR_ENV: &str = "DISCOBOT_LOG_DIR";
+
+/// Where the previous launch logged, beside the remembered ports.
+const LAST_LOG_DIR_FILE: &str = "last-log-dir";
+
+/// The log directory for this launch, chosen by `init_log_dir`.
+static LOG_DIR: OnceLock<Result<PathBuf, String>> = OnceLock::new();
+
+/// Lines the first run's log starts with, e.g. where earlier logs are. They're
+/// written before the next line the shell or a server writes to it.
+static NOTICES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+struct Run {
+    id: String,
+    /// A server has logged to this run, so the next one starts a new run.
+    server_started: bool,
+}
+
+/// The run being logged to, `None` until something is first logged.
+static CURRENT_RUN: Mutex<Option<Run>> = Mutex::new(None);
+
+/// Bumped whenever a new run starts, so a writer still on the previous one
+/// knows to move over.
+static RUN_CHANGES: AtomicU64 = AtomicU64::new(0);
+/// Held while the writer touches its files and while a new run starts, so
+/// the switch never lands in the middle of a write.
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+/// The run whose files the server's writer has open. It may still have
+/// lines buffered for them, so `compress_runs` leaves the run alone until a
+/// later tidy after the writer has moved on.
+static WRITER_RUN: Mutex<Option<String>> = Mutex::new(None);
+/// Held while earlier runs are compressed or pruned.
+static TIDY_LOCK: Mutex<()> = Mutex::new(());
+
+/// `Settings::split_log_streams`, kept here so the log writer can read it
+/// without access to the app's state.
+static SPLIT_STREAMS: AtomicBool = AtomicBool::new(false);
+
+/// Apply `Settings::split_log_streams`; called at startup and whenever
+/// settings change.
+pub fn set_split_streams(split: bool) {
+    SPLIT_STREAMS.store(split, Ordering::SeqCst);
+}
+
+/// Format of the current run's files, so the shell's own lines match the
+/// server writer's.
+static RUN_FORMAT: Mutex<LogFormat> = Mutex::new(LogFormat::Text);
+
+/// Write the shell's lines in `Settings::log_format` until a server opens the
+/// run's files; called at startup. A changed setting applies from the next
+/// server start, like the writer's.
+pub fn set_format(format: LogFormat) {
+    *RUN_FORMAT.lock().unwrap() = format;
+}
+
+/// Choose this launch's log directory from `DISCOBOT_LOG_DIR` or
+/// `Settings::log_dir`. Called once at startup, so a changed setting applies
+/// from the next launch and a run's files never end up in two places.
+pub fn init_log_dir(setting: Option<&str>) {
+    let dir = LOG_DIR
+        .get_or_init(|| choose_log_dir(setting))
+        .as_ref()
+        .ok();
+    shell_log::open(dir.map(PathBuf::as_path));
+    if let Some(dir) = dir {
+        note_move(dir);
+    }
+}
+
+/// The directory holding the logs of this launch.
+pub fn log_dir() -> Result<PathBuf, String> {
+    let dir = LOG_DIR.get_or_init(|| choose_log_dir(None)).clone()?;
+    // In case it was removed while the app was running
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create log directory {}: {}", dir.display(), e))?;
+    Ok(dir)
+}
+
+/// Check a `Settings::log_dir` before saving it. Whether it's writable is
+/// only known once it's used, at the next launch.
+pub fn validate_log_dir(dir: Option<&str>) -> Result<(), String> {
+    match dir.filter(|dir| !dir.is_empty()) {
+        Some(dir) if Path::new(dir).is_relative() => {
+            Err(format!("Log directory '{}' must be an absolute path", dir))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn default_log_dir() -> Result<PathBuf, String> {
+    // Try XDG_STATE_HOME first, fallback to XDG_DATA_HOME, then ~/.local/state
+    let state_dir = dirs::state_dir()
+        .or_else(dirs::data_dir)
+        .ok_or_else(|| "Could not determine state directory".to_string())?;
+    Ok(state_dir.join("discobot").join("logs"))
+}
+
+/// Create `dir` if needed and make sure files can be written in it.
+fn check_writable(dir: &Path) -> Result<(), String> {
+    if dir.is_relative() {
+        return Err(format!(
+            "Log directory {} must be an absolute path",
+            dir.display()
+        ));
+    }
+    fs::create_dir_all(dir)
+        .map_err(|e| format!("Failed to create log directory {}: {}", dir.display(), e))?;
+    let probe = dir.join(".write-test");
+    fs::write(&probe, b"")
+        .and_then(|()| fs::remove_file(&probe))
+        .map_err(|e| format!("Log directory {} isn't writable: {}", dir.display(), e))
+}
+
+/// The last resort for logs, in a directory only this user can reach, since
+/// the temp directory itself may be shared by all users, like `/tmp`.
+#[cfg(unix)]
+fn temp_log_dir() -> Result<PathBuf, String> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+
+    // SAFETY: geteuid has no preconditions and can't fail.
+    let uid = unsafe { libc::geteuid() };
+    let dir = std::env::temp_dir().join(format!("discobot-{}", uid));
+    match fs::DirBuilder::new().mode(0o700).create(&dir) {
+        Err(e) if e.kind() != ErrorKind::AlreadyExists => {
+            return Err(format!(
+                "Failed to create log directory {}: {}",
+                dir.display(),
+                e
+            ));
+        }
+        _ => {}
+    }
+    // Someone else may have created it first, or put a link there
+    let metadata = fs::symlink_metadata(&dir)
+        .map_err(|e| format!("Failed to inspect {}: {}", dir.display(), e))?;
+    if !metadata.is_dir() || metadata.uid() != uid || metadata.permissions().mode() & 0o077 != 0 {
+        return Err(format!(
+            "{} isn't a private directory owned by this user",
+            dir.display()
+        ));
+    }
+    Ok(dir.join("logs"))
+}
+
+/// The temp directory is already per user here.
+#[cfg(not(unix))]
+fn temp_log_dir() -> Result<PathBuf, String> {
+    Ok(std::env::temp_dir().join("discobot").join("logs"))
+}
+
+/// The configured log directory if it's usable, otherwise the default one,
+/// otherwise one under the temp directory (e.g. with a read-only home).
+/// Falling back is noted at the top of the first log.
+fn choose_log_dir(setting: Option<&str>) -> Result<PathBuf, String> {
+    let configured = std::env::var_os(LOG_DIR_ENV)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| setting.filter(|dir| !dir.is_empty()).map(PathBuf::from));
+    let candidates = configured
+        .map(Ok)
+        .into_iter()
+        .chain([default_log_dir(), temp_log_dir()]);
+    let mut errors = Vec::new();
+    for dir in candidates {
+        let dir = match dir.and_then(|dir| check_writable(&dir).map(|()| dir)) {
+            Ok(dir) => dir,
+            Err(e) => {
+                errors.push(e);
+                continue;
+            }
+        };
+        if !errors.is_empty() {
+            let notice = format!(
+                "{}, logging to {} instead",
+                errors.join("; "),
+                dir.display()
+            );
+            warn!("{}", notice);
+            NOTICES.lock().unwrap().push(notice);
+        }
+        return Ok(dir);
+    }
+    Err(errors.join("; "))
+}
+
+/// Note at the top of the first log where the previous launch's logs are,
+/// if they were elsewhere, and remember this launch's directory.
+fn note_move(dir: &Path) {
+    let Some(state_dir) = dirs::state_dir().or_else(dirs::data_dir) else {
+        return;
+    };
+    let path = state_dir.join("discobot").join(LAST_LOG_DIR_FILE);
+    let previous = fs::read_to_string(&path)
+        .ok()
+        .map(|previous| PathBuf::from(previous.trim_end()));
+    if previous.as_deref() == Some(dir) {
+        return;
+    }
+    if let Some(previous) = previous.filter(|previous| previous.is_dir()) {
+        NOTICES.lock().unwrap().push(format!(
+            "Logs from earlier launches are in {}",
+            previous.display()
+        ));
+    }
+    let saved = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|()| fs::write(&path, dir.to_string_lossy().as_bytes()));
+    if let Err(e) = saved {
+        warn!("Failed to remember the log directory: {}", e);
+    }
+}
+
+/// `server-<run><suffix>.log` in `dir`.
+fn log_path(dir: &Path, run: &str, suffix: &str) -> PathBuf {
+    dir.join(format!("{}{}{}{}", LOG_PREFIX, run, suffix, LOG_EXTENSION))
+}
+
+/// The stream suffixes of a new run's files.
+fn active_suffixes() -> &'static [&'static str] {
+    if SPLIT_STREAMS.load(Ordering::SeqCst) {
+        &[STDOUT_SUFFIX, STDERR_SUFFIX]
+    } else {
+        &[""]
+    }
+}
+
+fn valid_run_id(run: &str) -> bool {
+    !run.is_empty()
+        && run.len() <= MAX_RUN_ID_LENGTH
+        && run.chars().all(|c| c.is_ascii_digit() || c == '-')
+}
+
+/// The run and stream suffix of a log file name like
+/// `server-<run>.err.log.gz`, or `None` for other files, including the
+/// `server-latest` links and logs from before runs had their own files.
+fn parse_log_name(name: &str) -> Option<(&str, &str)> {
+    let name = name.strip_suffix(GZ_EXTENSION).unwrap_or(name);
+    let stem = name.strip_prefix(LOG_PREFIX)?.strip_suffix(LOG_EXTENSION)?;
+    let (run, suffix) = [STDOUT_SUFFIX, STDERR_SUFFIX]
+        .into_iter()
+        .find_map(|suffix| Some((stem.strip_suffix(suffix)?, suffix)))
+        .unwrap_or((stem, ""));
+    valid_run_id(run).then_some((run, suffix))
+}
+
+fn current_run_id() -> Option<String> {
+    CURRENT_RUN
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|run| run.id.clone())
+}
+
+/// The current run's id, starting a run if nothing has been logged yet, e.g.
+/// for the shell's own events before the first server start.
+fn current_run() -> String {
+    let mut current = CURRENT_RUN.lock().unwrap();
+    match &*current {
+        Some(run) => run.id.clone(),
+        None => start_run(&mut current, false),
+    }
+}
+
+/// Switch to a new run, creating its files and pointing the `server-latest`
+/// links at them. Called with `CURRENT_RUN` locked.
+fn start_run(current: &mut Option<Run>, server_started: bool) -> String {
+    let id = chrono::Local::now().format(RUN_ID_FORMAT).to_string();
+    *current = Some(Run {
+        id: id.clone(),
+        server_started,
+    });
+    RUN_CHANGES.fetch_add(1, Ordering::SeqCst);
+    match log_dir() {
+        Ok(dir) => link_latest(&dir, &id),
+        Err(e) => warn!("{}", e),
+    }
+    id
+}
+
+/// Give the next server its own run, unless nothing but the shell has logged
+/// to the current one yet.
+fn begin_server_run() {
+    let mut current = CURRENT_RUN.lock().unwrap();
+    if let Some(run) = current.as_mut().filter(|run| !run.server_started) {
+        run.server_started = true;
+        return;
+    }
+    start_run(&mut current, true);
+}
+
+/// Point `server-latest.log` (or `.out.log` and `.err.log`) at the run's
+/// files: a symlink where possible, otherwise a hard link, which Windows
+/// allows without extra privileges.
+fn link_latest(dir: &Path, run: &str) {
+    for suffix in ["", STDOUT_SUFFIX, STDERR_SUFFIX] {
+        if let Err(e) = remove_if_exists(&log_path(dir, LATEST_RUN, suffix)) {
+            warn!("{}", e);
+        }
+    }
+    for suffix in active_suffixes() {
+        let path = log_path(dir, run, suffix);
+        // A hard link needs the file to exist
+        if let Err(e) = open_append(&path) {
+            warn!("{}", e);
+            continue;
+        }
+        let link = log_path(dir, LATEST_RUN, suffix);
+        #[cfg(unix)]
+        let linked = std::os::unix::fs::symlink(path.file_name().unwrap_or_default(), &link);
+        #[cfg(not(unix))]
+        let linked = fs::hard_link(&path, &link);
+        if let Err(e) = linked {
+            warn!("Failed to link {}: {}", link.display(), e);
+        }
+    }
+}
+
+/// Path of the current run's `server-<run>.log`, which is under the temp
+/// directory if the log directory can't be created.
+pub fn get_current_log_file_path() -> Result<PathBuf, String> {
+    Ok(log_path(&log_dir()?, &current_run(), ""))
+}
+
+/// The files sidecar output goes to: the current run's `server-<run>.log`,
+/// or its `.out.log` and `.err.log` with `Settings::split_log_streams`.
+fn active_log_paths() -> Result<Vec<PathBuf>, String> {
+    let dir = log_dir()?;
+    let run = current_run();
+    Ok(active_suffixes()
+        .iter()
+        .map(|suffix| log_path(&dir, &run, suffix))
+        .collect())
+}
+
+/// The file with the server's own log messages, which it writes to stderr:
+/// `server-<run>.log`, or `server-<run>.err.log` when the streams are split.
+pub fn main_log_path() -> Result<PathBuf, String> {
+    let mut paths = active_log_paths()?;
+    Ok(paths.pop().unwrap_or_default())
+}
+
+/// The main file of a finished run as it exists on disk, compressed or not.
+fn run_main_path(dir: &Path, run: &str) -> Option<PathBuf> {
+    [STDERR_SUFFIX, "", STDOUT_SUFFIX]
+        .into_iter()
+        .map(|suffix| log_path(dir, run, suffix))
+        .flat_map(|path| [path.clone(), gz_path(&path)])
+        .find(|path| path.exists())
+}
+
+/// Open the current run's log in the system's default viewer.
+pub fn open_log_file(app: &tauri::AppHandle) {
+    let result = main_log_path().and_then(|path| {
+        app.opener()
+            .open_path(path.display().to_string(), None::<&str>)
+            .map_err(|e| format!("Failed to open log file: {}", e))
+    });
+    if let Err(e) = result {
+        warn!("{}", e);
+    }
+}
+
+/// Smallest `Settings::log_retention_bytes` accepted, so a typo can't delete
+/// every earlier run.
+const MIN_RETENTION_BYTES: u64 = 1024 * 1024;
+/// Most `Settings::log_retention_runs` accepted.
+const MAX_RETENTION_RUNS: u32 = 100;
+
+/// Override `Settings::log_retention_runs` and
+/// `Settings::log_retention_bytes`.
+const RETENTION_RUNS_ENV: &str = "DISCOBOT_LOG_RETENTION_RUNS";
+const RETENTION_BYTES_ENV: &str = "DISCOBOT_LOG_RETENTION_BYTES";
+
+/// Check configured log retention, e.g. before saving settings.
+pub fn validate_retention(runs: u32, bytes: u64) -> Result<(), String> {
+    if !(1..=MAX_RETENTION_RUNS).contains(&runs) {
+        return Err(format!(
+            "Number of runs to keep logs for must be between 1 and {}",
+            MAX_RETENTION_RUNS
+        ));
+    }
+    if bytes < MIN_RETENTION_BYTES {
+        return Err(format!(
+            "Log retention size must be at least {} bytes",
+            MIN_RETENTION_BYTES
+        ));
+    }
+    Ok(())
+}
+
+/// The value of an override environment variable, if set and valid.
+fn env_override<T: std::str::FromStr>(name: &str) -> Option<T> {
+    let value = std::env::var(name).ok().filter(|value| !value.is_empty())?;
+    let parsed = value.trim().parse().ok();
+    if parsed.is_none() {
+        warn!("Ignoring invalid {}={}", name, value);
+    }
+    parsed
+}
+
+/// Open the folder holding the logs of every run, creating it if needed.
+/// The error names the folder so the UI can show it instead.
+#[tauri::command]
+pub fn open_log_folder(app: tauri::AppHandle) -> Result<(), String> {
+    let dir = log_dir()?;
+    app.opener()
+        .open_path(dir.display().to_string(), None::<&str>)
+        .map_err(|e| format!("Failed to open log folder {}: {}", dir.display(), e))
+}
+
+/// How many earlier runs' logs to keep, from `Settings::log_retention_runs`,
+/// `Settings::log_retention_bytes` and `Settings::compress_previous_logs`, or
+/// `DISCOBOT_LOG_RETENTION_RUNS` and `DISCOBOT_LOG_RETENTION_BYTES`. Read
+/// whenever a run starts, so changes apply without a restart.
+#[derive(Clone, Copy, Serialize)]
+pub struct LogRetention {
+    runs: u32,
+    bytes: u64,
+    compress: bool,
+}
+
+impl LogRetention {
+    /// Use the configured retention if it makes sense, otherwise warn and
+    /// fall back to the defaults.
+    pub fn from_settings(settings: &Settings) -> Self {
+        let runs = env_override(RETENTION_RUNS_ENV).unwrap_or(settings.log_retention_runs);
+        let bytes = env_override(RETENTION_BYTES_ENV).unwrap_or(settings.log_retention_bytes);
+        let compress = settings.compress_previous_logs;
+        if let Err(e) = validate_retention(runs, bytes) {
+            warn!("{}, using the default log retention", e);
+            return Self {
+                compress,
+                ..Self::default()
+            };
+        }
+        Self {
+            runs,
+            bytes,
+            compress,
+        }
+    }
+}
+
+impl Default for LogRetention {
+    fn default() -> Self {
+        Self {
+            runs: DEFAULT_RETENTION_RUNS,
+            bytes: DEFAULT_RETENTION_BYTES,
+            compress: true,
+        }
+    }
+}
+
+/// The log retention in effect, for the settings UI.
+#[derive(Serialize)]
+pub struct LogConfig {
+    #[serde(flatten)]
+    retention: LogRetention,
+    /// Where this launch logs, which differs from `Settings::log_dir` after
+    /// a change until the next launch, or if it wasn't usable.
+    dir: Option<PathBuf>,
+    /// Set by `DISCOBOT_LOG_RETENTION_RUNS` rather than settings.
+    runs_overridden: bool,
+    /// Set by `DISCOBOT_LOG_RETENTION_BYTES` rather than settings.
+    bytes_overridden: bool,
+}
+
+#[tauri::command]
+pub fn get_log_config(settings: tauri::State<'_, Mutex<Settings>>) -> LogConfig {
+    LogConfig {
+        retention: LogRetention::from_settings(&settings.lock().unwrap()),
+        dir: log_dir().ok(),
+        runs_overridden: env_override::<u32>(RETENTION_RUNS_ENV).is_some(),
+        bytes_overridden: env_override::<u64>(RETENTION_BYTES_ENV).is_some(),
+    }
+}
+
+/// `server-<run>.log.gz` for `server-<run>.log`.
+fn gz_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(GZ_EXTENSION);
+    PathBuf::from(name)
+}
+
+fn is_gz(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "gz")
+}
+
+fn remove_if_exists(path: &Path) -> Result<(), String> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("Failed to remove {}: {}", path.display(), e)),
+    }
+}
+
+/// A run with log files.
+#[derive(Serialize)]
+pub struct LogRun {
+    /// For `get_server_logs`.
+    id: String,
+    current: bool,
+    /// Total size of its files on disk.
+    bytes: u64,
+    /// Its files, which are gzipped for earlier runs unless
+    /// `Settings::compress_previous_logs` is off.
+    files: Vec<PathBuf>,
+}
+
+/// The runs with files in `dir`, newest first.
+fn runs(dir: &Path) -> Result<Vec<LogRun>, String> {
+    let current = current_run_id();
+    let entries = fs::read_dir(dir).map_err(|e| format!("Failed to read log directory: {}", e))?;
+    let mut runs: BTreeMap<String, LogRun> = BTreeMap::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some((id, _)) = name.to_str().and_then(parse_log_name) else {
+            continue;
+        };
+        let run = runs.entry(id.to_string()).or_insert_with(|| LogRun {
+            id: id.to_string(),
+            current: current.as_deref() == Some(id),
+            bytes: 0,
+            files: Vec::new(),
+        });
+        run.bytes += entry.metadata().map_or(0, |metadata| metadata.len());
+        run.files.push(entry.path());
+    }
+    Ok(runs
+        .into_values()
+        .rev()
+        .map(|mut run| {
+            run.files.sort();
+            run
+        })
+        .collect())
+}
+
+/// The runs that have logs, newest first, e.g. to pick one to view with
+/// `get_server_logs`.
+#[tauri::command]
+pub fn list_log_runs() -> Result<Vec<LogRun>, String> {
+    runs(&log_dir()?)
+}
+
+/// In the background, gzip the files of earlier runs if enabled, then delete
+/// the oldest runs beyond the retention limits. The current run, and the one
+/// the writer may still be flushing, count toward the limits but are never
+/// touched.
+pub fn tidy_runs(retention: LogRetention) {
+    tauri::async_runtime::spawn_blocking(move || {
+        let _guard = TIDY_LOCK.lock().unwrap();
+        let dir = match log_dir() {
+            Ok(dir) => dir,
+            Err(e) => return warn!("{}", e),
+        };
+        let in_use = runs_in_use();
+        if retention.compress {
+            compress_runs(&dir, &in_use);
+        }
+        if let Err(e) = prune_runs(&dir, retention, &in_use) {
+            warn!("{}", e);
+        }
+    });
+}
+
+/// The current run and the one the writer has open, which may still have
+/// buffered lines for its files.
+fn runs_in_use() -> Vec<String> {
+    current_run_id()
+        .into_iter()
+        .chain(WRITER_RUN.lock().unwrap().clone())
+        .collect()
+}
+
+/// Gzip every file of a run not `in_use` that isn't yet, including any left
+/// over from an interrupted run. The plain file is only removed once its
+/// `.gz` is completely written.
+fn compress_runs(dir: &Path, in_use: &[String]) {
+    let Ok(runs) = runs(dir) else {
+        return;
+    };
+    let files = runs
+        .iter()
+        .filter(|run| !in_use.contains(&run.id))
+        .flat_map(|run| &run.files)
+        .filter(|path| !is_gz(path));
+    for plain in files {
+        let compressed = gz_path(plain);
+        if let Err(e) = compress_file(plain, &compressed) {
+            warn!("{}", e);
+            let _ = fs::remove_file(&compressed);
+            continue;
+        }
+        if let Err(e) = fs::remove_file(plain) {
+            warn!("Failed to remove {}: {}", plain.display(), e);
+        }
+    }
+}
+
+/// Keep the newest runs while they fit within `retention` and delete the
+/// rest, except those `in_use`.
+fn prune_runs(dir: &Path, retention: LogRetention, in_use: &[String]) -> Result<(), String> {
+    let (mut kept, mut bytes) = (0, 0);
+    let mut full = false;
+    for run in runs(dir)? {
+        if !in_use.contains(&run.id) {
+            full = full || kept >= retention.runs || bytes + run.bytes > retention.bytes;
+            if full {
+                for file in &run.files {
+                    remove_if_exists(file)?;
+                }
+                continue;
+            }
+        }
+        kept += 1;
+        bytes += run.bytes;
+    }
+    Ok(())
+}
+
+fn compress_file(from: &Path, to: &Path) -> Result<(), String> {
+    let mut input =
+        File::open(from).map_err(|e| format!("Failed to open {}: {}", from.display(), e))?;
+    let output =
+        File::create(to).map_err(|e| format!("Failed to create {}: {}", to.display(), e))?;
+    let mut encoder = GzEncoder::new(BufWriter::new(output), Compression::default());
+    std::io::copy(&mut input, &mut encoder)
+        .and_then(|_| encoder.finish())
+        .and_then(|mut output| {
+            output.flush()?;
+            output.get_ref().sync_all()
+        })
+        .map_err(|e| format!("Failed to compress {}: {}", from.display(), e))
+}
+
+/// The current run's files, then those of earlier runs newest first, then
+/// the shell's own logs, for diagnostics. Earlier runs' files may be
+/// gzipped.
+pub fn log_files() -> Vec<PathBuf> {
+    let Ok(dir) = log_dir() else {
+        return Vec::new();
+    };
+    runs(&dir)
+        .unwrap_or_default()
+        .into_iter()
+        .flat_map(|run| run.files)
+        .chain(shell_log::files(&dir))
+        .collect()
+}
+
+/// Append a message from the shell to the current run's log, in the run's
+/// format like the writer's `discobot` lines, so events from both sides
+/// interleave readably and a JSON log stays one object per line.
+pub fn append_server_log(message: &str) {
+    // Like the writer, so the run can't change halfway through
+    let _guard = WRITE_LOCK.lock().unwrap();
+    let Ok(mut file) = main_log_path().and_then(|path| open_append(&path)) else {
+        return;
+    };
+    let format = *RUN_FORMAT.lock().unwrap();
+    for message in take_notices().iter().map(String::as_str).chain([message]) {
+        if let Some(line) = shell_line(format, message) {
+            let _ = writeln!(file, "{}", line);
+        }
+    }
+}
+
+fn take_notices() -> Vec<String> {
+    std::mem::take(&mut *NOTICES.lock().unwrap())
+}
+
+/// A line from the shell itself, e.g. a lifecycle event, as `ServerLog`
+/// writes them in `format`.
+fn shell_line(format: LogFormat, message: &str) -> Option<String> {
+    match format {
+        LogFormat::Text => {
+            let timestamp = chrono::Local::now().format(TEXT_TIME_FORMAT);
+            Some(format!("{} [discobot] {}", timestamp, message))
+        }
+        LogFormat::Json => serde_json::to_string(&JsonLine {
+            ts: chrono::Local::now().to_rfc3339(),
+            stream: "discobot",
+            msg: message,
+        })
+        .ok(),
+    }
+}
+
+/// Empty the log files, e.g. before reproducing a bug, leaving a header line.
+///
+/// The running server's writer opened the files in append mode, so it keeps
+/// writing at the new end of each file instead of leaving a hole.
+#[tauri::command]
+pub fn clear_server_log(settings: tauri::State<'_, Mutex<Settings>>) -> Result<(), String> {
+    let files = active_log_paths()?
+        .iter()
+        .map(|path| {
+            OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(path)
+                .map_err(|e| format!("Failed to clear log file: {}", e))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut log = ServerLog::new(files, settings.lock().unwrap().log_format, Duration::ZERO);
+    match log.format {
+        LogFormat::Text => {
+            let timestamp = chrono::Local::now().format(TEXT_TIME_FORMAT);
+            log.write_raw(&format!("=== Log cleared at {} ===", timestamp));
+        }
+        LogFormat::Json => log.write_line("discobot", "Log cleared"),
+    }
+    log.flush();
+    Ok(())
+}
+
+/// Start a new run now, e.g. before reproducing a bug so its log stands on
+/// its own, and return its id. The running server's writer finishes its
+/// current write first and then moves over to the new files.
+#[tauri::command]
+pub fn truncate_logs_now(settings: tauri::State<'_, Mutex<Settings>>) -> String {
+    let retention = LogRetention::from_settings(&settings.lock().unwrap());
+    let run = {
+        let _guard = WRITE_LOCK.lock().unwrap();
+        start_run(&mut CURRENT_RUN.lock().unwrap(), true)
+    };
+    tidy_runs(retention);
+    run
+}
+
+/// Which log `get_server_logs` reads.
+#[derive(Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogSource {
+    /// The server's output, in server.log.
+    #[default]
+    Server,
+    /// The app's own diagnostics, in shell.log.
+    Shell,
+}
+
+/// The last `lines` lines, at most 5000, of the server's own log for `run`
+/// (an id from `list_log_runs`) or the current run, oldest first. Lines the
+/// server's writer still has buffered aren't included yet. With `source`
+/// set to `shell`, the end of the app's own shell.log instead, which isn't
+/// kept per run.
+#[tauri::command]
+pub fn get_server_logs(
+    lines: usize,
+    run: Option<String>,
+    source: Option<LogSource>,
+) -> Result<Vec<String>, String> {
+    let lines = lines.min(MAX_TAIL_LINES);
+    let source = source.unwrap_or_default();
+    if source == LogSource::Shell && run.is_some() {
+        return Err("The shell log isn't kept per run".to_string());
+    }
+    if let Some(run) = run.as_deref().filter(|run| !valid_run_id(run)) {
+        return Err(format!("Invalid log run '{}'", run));
+    }
+    for _ in 0..TAIL_ATTEMPTS {
+        let earlier = run
+            .as_ref()
+            .filter(|run| Some(*run) != current_run_id().as_ref());
+        let path = match (source, earlier) {
+            (LogSource::Shell, _) => shell_log::path(&log_dir()?),
+            (LogSource::Server, Some(run)) => run_main_path(&log_dir()?, run)
+                .ok_or_else(|| format!("No logs are left for run {}", run))?,
+            (LogSource::Server, None) => main_log_path()?,
+        };
+        // Reading from an open handle keeps a new run's compression from
+        // pulling the file away mid-read; a clear shows up as a short read,
+        // so start over
+        let tail = if is_gz(&path) {
+            read_last_gz_lines(&path, lines).map(Some)
+        } else {
+            read_last_lines(&path, lines)
+        };
+        match tail {
+            Ok(Some(tail)) => return Ok(tail),
+            Ok(None) => continue,
+            // Compressed since it was found
+            Err(e) if e.kind() == ErrorKind::NotFound && earlier.is_some() => continue,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("Failed to read log file: {}", e)),
+        }
+    }
+    Err("Failed to read log file: it kept changing while being read".to_string())
+}
+
+/// `read_last_lines` for a gzipped file, which has to be read from the
+/// start.
+fn read_last_gz_lines(path: &Path, lines: usize) -> std::io::Result<Vec<String>> {
+    let mut tail = VecDeque::new();
+    if lines == 0 {
+        return Ok(Vec::new());
+    }
+    for line in BufReader::new(GzDecoder::new(File::open(path)?)).split(b'\n') {
+        if tail.len() == lines {
+            tail.pop_front();
+        }
+        let line = String::from_utf8_lossy(&line?).into_owned();
+        tail.push_back(line.trim_end_matches('\r').to_string());
+    }
+    Ok(tail.into())
+}
+
+/// Read backwards from the end of the file until it has `lines` complete
+/// lines. `None` if the file was truncated while reading.
+fn read_last_lines(path: &Path, lines: usize) -> std::io::Result<Option<Vec<String>>> {
+    if lines == 0 {
+        return Ok(Some(Vec::new()));
+    }
+    let mut file = File::open(path)?;
+    let mut start = file.metadata()?.len();
+    let mut tail = Vec::new();
+    // One more newline than lines, to know the first one is complete
+    while start > 0 && tail.iter().filter(|&&byte| byte == b'\n').count() <= lines {
+        let chunk = TAIL_CHUNK_BYTES.min(start);
+        start -= chunk;
+        file.seek(SeekFrom::Start(start))?;
+        let mut buf = Vec::with_capacity(chunk as usize);
+        (&mut file).take(chunk).read_to_end(&mut buf)?;
+        if (buf.len() as u64) < chunk {
+            return Ok(None);
+        }
+        buf.extend_from_slice(&tail);
+        tail = buf;
+    }
+
+    let text = String::from_utf8_lossy(&tail);
+    let all: Vec<&str> = text.lines().collect();
+    // Unless the whole file was read, the first line may be cut off
+    let complete = if start > 0 { &all[1..] } else { &all[..] };
+    let skip = complete.len().saturating_sub(lines);
+    Ok(Some(
+        complete[skip..]
+            .iter()
+            .map(|line| line.to_string())
+            .collect(),
+    ))
+}
+
+/// A line of sidecar output in `LogFormat::Json`.
+#[derive(Serialize)]
+struct JsonLine<'a> {
+    /// RFC 3339 (ISO-8601) timestamp of when the shell received the line.
+    ts: String,
+    stream: &'a str,
+    msg: &'a str,
+}
+
+fn open_append(path: &Path) -> Result<File, String> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open log file {}: {}", path.display(), e))
+}
+
+/// Open the current run's files for the server's writer and note the run in
+/// `WRITER_RUN`. Called with `WRITE_LOCK` held, so the run can't change in
+/// between.
+fn open_active_files() -> Result<Vec<File>, String> {
+    let files = active_log_paths()?
+        .iter()
+        .map(|path| open_append(path))
+        .collect::<Result<Vec<_>, _>>()?;
+    *WRITER_RUN.lock().unwrap() = current_run_id();
+    Ok(files)
+}
+
+/// Writer for the sidecar's stdout/stderr, opened once per server run.
+///
+/// Lines are buffered and written out (and synced) by `flush`, which the
+/// supervisor calls at `deadline`, so chatty output doesn't cost a syscall
+/// per line.
+pub struct ServerLog {
+    /// The run's `server-<run>.log`, or its `.out.log` and `.err.log` when
+    /// the streams are split. Empty if they couldn't be opened.
+    files: Vec<BufWriter<File>>,
+    format: LogFormat,
+    flush_interval: Duration,
+    buffered_lines: usize,
+    deadline: Option<tokio::time::Instant>,
+    /// `RUN_CHANGES` when `files` were opened.
+    run_changes: u64,
+}
+
+impl ServerLog {
+    /// A writer for files opened by the caller, from `active_log_paths`.
+    fn new(files: Vec<File>, format: LogFormat, flush_interval: Duration) -> Self {
+        Self {
+            files: files.into_iter().map(BufWriter::new).collect(),
+            format,
+            flush_interval,
+            buffered_lines: 0,
+            deadline: None,
+            run_changes: RUN_CHANGES.load(Ordering::SeqCst),
+        }
+    }
+
+    /// Start a run for a new server and open its files for appending, then
+    /// tidy up earlier runs in the background.
+    pub fn open(format: LogFormat, retention: LogRetention, flush_interval: Duration) -> Self {
+        let mut log = {
+            let _guard = WRITE_LOCK.lock().unwrap();
+            begin_server_run();
+            set_format(format);
+            match open_active_files() {
+                Ok(files) => Self::new(files, format, flush_interval),
+                Err(e) => {
+                    warn!("Server output will not be logged: {}", e);
+                    Self::new(Vec::new(), format, flush_interval)
+                }
+            }
+        };
+        tidy_runs(retention);
+        for notice in take_notices() {
+            log.write_line("discobot", &notice);
+        }
+        log
+    }
+
+    /// Write a line of sidecar output. JSON records from the server are
+    /// rendered readably in the text format and dropped below
+    /// `Settings::log_file_level`; other lines are written as they are.
+    pub fn write_line(&mut self, stream: &str, line: &str) {
+        match self.format {
+            LogFormat::Text => {
+                // Only in the file; live log events carry their own `ts`
+                let timestamp = chrono::Local::now().format(TEXT_TIME_FORMAT);
+                for line in line.split('\n') {
+                    let line = line.trim_end_matches('\r');
+                    let Some(record) = structured_logs::Record::parse(line) else {
+                        self.write_to(stream, &format!("{} [{}] {}", timestamp, stream, line));
+                        continue;
+                    };
+                    if record.filtered() {
+                        continue;
+                    }
+                    if structured_logs::render_enabled() {
+                        let time = record.time().map_or(timestamp.to_string(), str::to_string);
+                        self.write_to(
+                            stream,
+                            &format!("{} [{}] {}", time, stream, record.render()),
+                        );
+                    } else {
+                        self.write_to(stream, &format!("{} [{}] {}", timestamp, stream, line));
+                    }
+                }
+            }
+            LogFormat::Json => {
+                let ts = chrono::Local::now().to_rfc3339();
+                // One object per line keeps the file greppable
+                for msg in line.lines() {
+                    if structured_logs::Record::parse(msg).is_some_and(|record| record.filtered()) {
+                        continue;
+                    }
+                    let entry = JsonLine {
+                        ts: ts.clone(),
+                        stream,
+                        msg,
+                    };
+                    if let Ok(json) = serde_json::to_string(&entry) {
+                        self.write_to(stream, &json);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Write a line to every file, e.g. a separator.
+    fn write_raw(&mut self, line: &str) {
+        self.write_to("discobot", line);
+    }
+
+    /// Write a line to the file for `stream`. With split streams, stdout and
+    /// control replies go to the `.out.log`, stderr to the `.err.log`, and
+    /// the shell's own lines to both.
+    fn write_to(&mut self, stream: &str, line: &str) {
+        let _guard = WRITE_LOCK.lock().unwrap();
+        self.reopen_if_new_run();
+        if self.files.is_empty() {
+            return;
+        }
+        let last = self.files.len() - 1;
+        let targets = match stream {
+            "stdout" | "control" => 0..=0,
+            "stderr" | "error" => last..=last,
+            _ => 0..=last,
+        };
+        for file in &mut self.files[targets] {
+            let _ = writeln!(file, "{}", line);
+        }
+        self.buffered_lines += 1;
+        if self.buffered_lines >= MAX_BUFFERED_LINES {
+            self.flush_file();
+        } else if self.deadline.is_none() {
+            self.deadline = Some(tokio::time::Instant::now() + self.flush_interval);
+        }
+    }
+
+    /// When buffered lines must be flushed, if there are any.
+    pub fn deadline(&self) -> Option<tokio::time::Instant> {
+        self.deadline
+    }
+
+    /// Write out buffered lines and sync them to disk.
+    pub fn flush(&mut self) {
+        let _guard = WRITE_LOCK.lock().unwrap();
+        self.reopen_if_new_run();
+        self.flush_file();
+    }
+
+    /// `flush` for callers already holding `WRITE_LOCK`.
+    fn flush_file(&mut self) {
+        self.deadline = None;
+        self.buffered_lines = 0;
+        for file in &mut self.files {
+            let _ = file.flush();
+            let _ = file.get_ref().sync_data();
+        }
+    }
+
+    /// After `truncate_logs_now`, write out what's buffered for the previous
+    /// run and carry on in the new one's files. Called with `WRITE_LOCK` held.
+    fn reopen_if_new_run(&mut self) {
+        let run_changes = RUN_CHANGES.load(Ordering::SeqCst);
+        if run_changes == self.run_changes {
+            return;
+        }
+        self.run_changes = run_changes;
+        for file in &mut self.files {
+            let _ = file.flush();
+        }
+        match open_active_files() {
+            Ok(files) => self.files = files.into_iter().map(BufWriter::new).collect(),
+            Err(e) => warn!("{}, writing to the previous run's log", e),
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct LogLine {
+    stream: &'static str,
+    line: String,
+    /// RFC 3339 timestamp of when the shell received the line.
+    ts: String,
+}
+
+/// Accumulates output lines so they can be forwarded to the webview in
+/// batches instead of one IPC message per line.
+#[derive(Default)]
+pub struct LogBatch {
+    lines: Vec<LogLine>,
+    deadline: Option<tokio::time::Instant>,
+}
+
+impl LogBatch {
+    /// When the pending lines must be flushed, if there are any.
+    pub fn deadline(&self) -> Option<tokio::time::Instant> {
+        self.deadline
+    }
+
+    pub fn push(&mut self, app: &tauri::AppHandle, stream: &'static str, line: &str) {
+        self.lines.push(LogLine {
+            stream,
+            line: line.to_string(),
+            ts: chrono::Local::now().to_rfc3339(),
+        });
+        if self.lines.len() >= MAX_BATCH_LINES {
+            self.flush(app);
+        } else if self.deadline.is_none() {
+            self.deadline = Some(tokio::time::Instant::now() + BATCH_WINDOW);
+        }
+    }
+
+    pub fn flush(&mut self, app: &tauri::AppHandle) {
+        self.deadline = None;
+        if self.lines.is_empty() {
+            return;
+        }
+        let lines = std::mem::take(&mut self.lines);
+        let _ = app.emit(SERVER_LOG_EVENT, lines);
+    }
+}
+
+struct TailSubscriber {
+    id: u64,
+    /// Webview that subscribed, so its subscriptions end with it.
+    label: String,
+    channel: Channel<String>,
+}
+
+#[derive(Default)]
+struct TailState {
+    backlog: VecDeque<String>,
+    subscribers: Vec<TailSubscriber>,
+    next_id: u64,
+}
+
+/// Live sidecar output for `tail_server_logs` subscribers, fed by the
+/// supervisor alongside the log files so nothing has to watch them.
+#[derive(Default)]
+pub struct LogTail(Mutex<TailState>);
+
+impl LogTail {
+    /// Send a formatted line to every subscriber, dropping those whose
+    /// channel is gone.
+    pub fn push(&self, line: String) {
+        let mut tail = self.0.lock().unwrap();
+        tail.subscribers
+            .retain(|subscriber| subscriber.channel.send(line.clone()).is_ok());
+        if tail.backlog.len() >= TAIL_BACKLOG_LINES {
+            tail.backlog.pop_front();
+        }
+        tail.backlog.push_back(line);
+    }
+
+    fn subscribe(&self, label: &str, channel: Channel<String>) -> Result<u64, String> {
+        let mut tail = self.0.lock().unwrap();
+        // Under the lock so no line is missed or sent twice
+        for line in &tail.backlog {
+            channel
+                .send(line.clone())
+                .map_err(|e| format!("Failed to send log lines: {}", e))?;
+        }
+        tail.next_id += 1;
+        let id = tail.next_id;
+        tail.subscribers.push(TailSubscriber {
+            id,
+            label: label.to_string(),
+            channel,
+        });
+        Ok(id)
+    }
+
+    fn unsubscribe(&self, id: u64) {
+        self.0
+            .lock()
+            .unwrap()
+            .subscribers
+            .retain(|subscriber| subscriber.id != id);
+    }
+
+    /// End the subscriptions of a closed webview.
+    pub fn remove_webview(&self, label: &str) {
+        self.0
+            .lock()
+            .unwrap()
+            .subscribers
+            .retain(|subscriber| subscriber.label != label);
+    }
+
+    /// A reload drops the page's channels.
+    pub fn on_page_load(&self, webview: &Webview, payload: &PageLoadPayload<'_>) {
+        if payload.event() == PageLoadEvent::Started {
+            self.remove_webview(webview.label());
+        }
+    }
+}
+
+/// Stream sidecar output to `channel` as it arrives, starting with the most
+/// recent 500 lines. Returns an id for `untail_server_logs`; the stream also
+/// ends when the webview reloads or closes.
+#[tauri::command]
+pub fn tail_server_logs(
+    webview: Webview,
+    tail: tauri::State<'_, LogTail>,
+    channel: Channel<String>,
+) -> Result<u64, String> {
+    tail.subscribe(webview.label(), channel)
+}
+
+#[tauri::command]
+pub fn untail_server_logs(tail: tauri::State<'_, LogTail>, id: u64) {
+    tail.unsubscribe(id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory for one test's log files.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "discobot-logs-test-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Write `bytes` bytes to each of `run`'s files, one per suffix.
+    fn write_run(dir: &Path, run: &str, suffixes: &[&str], bytes: usize) {
+        for suffix in suffixes {
+            fs::write(log_path(dir, run, suffix), vec![b'x'; bytes]).unwrap();
+        }
+    }
+
+    fn run_ids(dir: &Path) -> Vec<String> {
+        runs(dir).unwrap().into_iter().map(|run| run.id).collect()
+    }
+
+    fn retention(runs: u32, bytes: u64) -> LogRetention {
+        LogRetention {
+            runs,
+            bytes,
+            compress: false,
+        }
+    }
+
+    const RUNS: [&str; 4] = [
+        "20260101-000000-001",
+        "20260101-000000-002",
+        "20260101-000000-003",
+        "20260101-000000-004",
+    ];
+
+    #[test]
+    fn prune_keeps_the_newest_runs_within_the_run_limit() {
+        let dir = test_dir("run-limit");
+        for run in RUNS {
+            write_run(&dir, run, &[""], 10);
+        }
+        prune_runs(&dir, retention(2, u64::MAX), &[]).unwrap();
+        assert_eq!(run_ids(&dir), [RUNS[3], RUNS[2]]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn prune_keeps_the_newest_runs_within_the_byte_limit() {
+        let dir = test_dir("byte-limit");
+        for run in RUNS {
+            // Split streams count together
+            write_run(&dir, run, &[STDOUT_SUFFIX, STDERR_SUFFIX], 50);
+        }
+        prune_runs(&dir, retention(10, 250), &[]).unwrap();
+        assert_eq!(run_ids(&dir), [RUNS[3], RUNS[2]]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn prune_never_deletes_runs_in_use() {
+        let dir = test_dir("in-use");
+        for run in RUNS {
+            write_run(&dir, run, &[""], 100);
+        }
+        let in_use = [RUNS[0].to_string(), RUNS[1].to_string()];
+        prune_runs(&dir, retention(1, 1), &in_use).unwrap();
+        assert_eq!(run_ids(&dir), [RUNS[1], RUNS[0]]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn prune_leaves_other_files_alone() {
+        let dir = test_dir("other-files");
+        write_run(&dir, RUNS[0], &[""], 10);
+        fs::write(dir.join("server.log"), b"old").unwrap();
+        fs::write(shell_log::path(&dir), b"shell").unwrap();
+        prune_runs(&dir, retention(1, 1), &[]).unwrap();
+        assert!(run_ids(&dir).is_empty());
+        assert!(dir.join("server.log").exists());
+        assert!(shell_log::path(&dir).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn compress_skips_runs_in_use() {
+        let dir = test_dir("compress");
+        write_run(&dir, RUNS[0], &[""], 10);
+        write_run(&dir, RUNS[1], &[""], 10);
+        compress_runs(&dir, &[RUNS[1].to_string()]);
+        assert!(gz_path(&log_path(&dir, RUNS[0], "")).exists());
+        assert!(!log_path(&dir, RUNS[0], "").exists());
+        assert!(log_path(&dir, RUNS[1], "").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn log_names_parse_to_run_and_stream() {
+        assert_eq!(
+            parse_log_name("server-20260101-000000-001.log"),
+            Some(("20260101-000000-001", ""))
+        );
+        assert_eq!(
+            parse_log_name("server-20260101-000000-001.err.log.gz"),
+            Some(("20260101-000000-001", STDERR_SUFFIX))
+        );
+        assert_eq!(parse_log_name("server-latest.log"), None);
+        assert_eq!(parse_log_name("server.log"), None);
+        assert_eq!(parse_log_name("shell.log"), None);
+    }
+}
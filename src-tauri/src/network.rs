@@ -0,0 +1,92 @@
+use std::net::{IpAddr, UdpSocket};
+use std::thread;
+use std::time::Duration;
+
+use log::info;
+use serde::Serialize;
+use tauri::Emitter;
+
+use crate::logs::append_server_log;
+
+/// How often connectivity is sampled.
+const POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+/// A new network state must be seen this many polls in a row before it's
+/// reported, so flapping interfaces don't spam the server and frontend.
+const DEBOUNCE_POLLS: u32 = 2;
+
+/// Emitted when connectivity changes; the frontend relays it to the server.
+const NETWORK_CHANGED_EVENT: &str = "network-changed";
+
+/// Public resolvers used only for route selection; no packets are sent.
+const ROUTE_PROBE_TARGETS: [&str; 2] = ["1.1.1.1:53", "[2606:4700:4700::1111]:53"];
+
+#[derive(Clone, PartialEq, Serialize)]
+struct NetworkState {
+    online: bool,
+    /// Local address of the default route, which changes when switching networks.
+    local_address: Option<String>,
+}
+
+/// Find the local address the OS would use to reach the internet.
+///
+/// Connecting a UDP socket only performs a route lookup, so this is cheap and
+/// doesn't touch the network. No route means we're offline.
+fn default_route_address() -> Option<IpAddr> {
+    ROUTE_PROBE_TARGETS.iter().find_map(|target| {
+        let bind = if target.starts_with('[') {
+            "[::]:0"
+        } else {
+            "0.0.0.0:0"
+        };
+        let socket = UdpSocket::bind(bind).ok()?;
+        socket.connect(target).ok()?;
+        socket.local_addr().ok().map(|addr| addr.ip())
+    })
+}
+
+fn probe() -> NetworkState {
+    let address = default_route_address();
+    NetworkState {
+        online: address.is_some(),
+        local_address: address.map(|ip| ip.to_string()),
+    }
+}
+
+/// Poll for connectivity changes and forward debounced transitions to the
+/// frontend, logging them to server.log so they line up with server errors.
+pub fn spawn_network_monitor(app: tauri::AppHandle) {
+    thread::spawn(move || {
+        let mut current = probe();
+        let mut pending: Option<(NetworkState, u32)> = None;
+        loop {
+            thread::sleep(POLL_INTERVAL);
+
+            let observed = probe();
+            if observed == current {
+                pending = None;
+                continue;
+            }
+
+            let seen = match &pending {
+                Some((state, count)) if *state == observed => count + 1,
+                _ => 1,
+            };
+            if seen < DEBOUNCE_POLLS {
+                pending = Some((observed, seen));
+                continue;
+            }
+
+            let message = match &observed.local_address {
+                Some(address) => format!("Network changed: online via {}", address),
+                None => "Network changed: offline".to_string(),
+            };
+            info!("{}", message);
+            append_server_log(&message);
+            let _ = app.emit(NETWORK_CHANGED_EVENT, observed.clone());
+
+            current = observed;
+            pending = None;
+        }
+    });
+}
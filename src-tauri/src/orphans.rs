@@ -0,0 +1,108 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use log::info;
+use serde::Serialize;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessStatus, ProcessesToUpdate, System, UpdateKind};
+use tauri::Manager;
+
+use crate::settings::Settings;
+use crate::{process_tree, sidecar, ServerState};
+
+/// A sidecar process left behind by an earlier run.
+#[derive(Serialize)]
+pub struct Orphan {
+    pid: u32,
+    path: PathBuf,
+    /// Whether it was terminated, always `false` when only listing.
+    killed: bool,
+}
+
+/// Paths of the bundled sidecars this app could have launched: the default
+/// one and the configured profile's. A `Settings::server_path` override is
+/// left out, since any process could be running that binary.
+fn bundled_paths(settings: &Settings) -> Vec<PathBuf> {
+    let mut names = vec![sidecar::NAME.to_string()];
+    if let Ok(profile) = sidecar::profile_binary(settings.server_profile.as_deref()) {
+        names.push(profile);
+    }
+    names
+        .iter()
+        .filter_map(|name| sidecar::path(name).ok())
+        .filter_map(|path| path.canonicalize().ok())
+        .collect()
+}
+
+fn same_file(exe: Option<&Path>, path: &Path) -> bool {
+    exe.and_then(|exe| exe.canonicalize().ok())
+        .is_some_and(|exe| exe == path)
+}
+
+/// Processes running one of `paths` that aren't `current`, the sidecar this
+/// app is running, and whose parent isn't a running copy of this app.
+fn find(system: &System, paths: &[PathBuf], current: Option<Pid>) -> Vec<(Pid, PathBuf)> {
+    let app_exe = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.canonicalize().ok());
+    system
+        .processes()
+        .iter()
+        .filter(|(pid, _)| Some(**pid) != current)
+        .filter_map(|(pid, process)| {
+            let path = paths.iter().find(|path| same_file(process.exe(), path))?;
+            let parent = process.parent().and_then(|parent| system.process(parent));
+            let owned = parent.is_some_and(|parent| {
+                app_exe
+                    .as_deref()
+                    .is_some_and(|app_exe| same_file(parent.exe(), app_exe))
+            });
+            (!owned).then(|| (*pid, path.clone()))
+        })
+        .collect()
+}
+
+/// List sidecar processes leaked by an earlier run (after a crash or a
+/// SIGKILL) that may still hold the server's ports, and terminate them along
+/// with their children if `terminate` is set. Only processes running the
+/// exact bundled binary are considered.
+#[tauri::command]
+pub async fn cleanup_orphans(
+    app: tauri::AppHandle,
+    terminate: bool,
+) -> Result<Vec<Orphan>, String> {
+    let paths = bundled_paths(&app.state::<Mutex<Settings>>().lock().unwrap());
+    let current = app
+        .state::<Mutex<ServerState>>()
+        .lock()
+        .unwrap()
+        .process
+        .as_ref()
+        .map(|child| Pid::from_u32(child.pid()));
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut system = System::new();
+        let refresh = ProcessRefreshKind::nothing().with_exe(UpdateKind::Always);
+        system.refresh_processes_specifics(ProcessesToUpdate::All, true, refresh);
+        let found = find(&system, &paths, current);
+
+        let mut orphans = Vec::new();
+        for (pid, path) in found {
+            let killed = terminate && {
+                info!("Terminating orphaned server process {}", pid);
+                process_tree::kill_tree(pid.as_u32());
+                system.refresh_processes_specifics(ProcessesToUpdate::Some(&[pid]), true, refresh);
+                system
+                    .process(pid)
+                    .is_none_or(|process| process.status() == ProcessStatus::Zombie)
+            };
+            orphans.push(Orphan {
+                pid: pid.as_u32(),
+                path,
+                killed,
+            });
+        }
+        orphans
+    })
+    .await
+    .map_err(|e| format!("Failed to look for orphaned server processes: {}", e))
+}
@@ -0,0 +1,123 @@
+use std::net::TcpListener;
+
+use log::warn;
+
+use crate::settings::{self, PortRange};
+
+/// Overrides `Settings::port_range`, as `start-end`.
+const PORT_RANGE_ENV: &str = "DISCOBOT_PORT_RANGE";
+
+impl PortRange {
+    pub fn contains(&self, port: u16) -> bool {
+        (self.start..=self.end).contains(&port)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        settings::validate_fixed_port(self.start)?;
+        if self.start > self.end {
+            return Err(format!(
+                "Port range {}-{} ends before it starts",
+                self.start, self.end
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Check a port range from the settings.
+pub fn validate(range: Option<PortRange>) -> Result<(), String> {
+    range.map_or(Ok(()), |range| range.validate())
+}
+
+/// Parse a `start-end` range, without checking it.
+fn parse(value: &str) -> Option<PortRange> {
+    let (start, end) = value.split_once('-')?;
+    Some(PortRange {
+        start: start.trim().parse().ok()?,
+        end: end.trim().parse().ok()?,
+    })
+}
+
+/// The port range from `DISCOBOT_PORT_RANGE` or the settings. An invalid range
+/// is ignored with a warning, leaving ports unconstrained.
+pub fn range(setting: Option<PortRange>) -> Option<PortRange> {
+    let range = match std::env::var(PORT_RANGE_ENV) {
+        Ok(value) => match parse(&value) {
+            Some(range) => range,
+            None => {
+                warn!("Ignoring {}={}: expected start-end", PORT_RANGE_ENV, value);
+                return None;
+            }
+        },
+        Err(_) => setting?,
+    };
+    match range.validate() {
+        Ok(()) => Some(range),
+        Err(e) => {
+            warn!("Ignoring port range: {}", e);
+            None
+        }
+    }
+}
+
+/// Bind the first free port of `preferred`, then of `range`, and only then
+/// let the OS pick one, with a warning if that leaves the range.
+pub fn bind(
+    preferred: impl IntoIterator<Item = u16>,
+    range: Option<PortRange>,
+) -> Option<TcpListener> {
+    let try_bind = |port: u16| TcpListener::bind(("127.0.0.1", port)).ok();
+    if let Some(listener) = preferred.into_iter().find_map(try_bind) {
+        return Some(listener);
+    }
+    if let Some(range) = range {
+        if let Some(listener) = (range.start..=range.end).find_map(try_bind) {
+            return Some(listener);
+        }
+        warn!(
+            "Every port in {}-{} is in use, using a random one",
+            range.start, range.end
+        );
+    }
+    try_bind(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ports(start: u16, end: u16) -> PortRange {
+        PortRange { start, end }
+    }
+
+    #[test]
+    fn parses_start_and_end() {
+        assert_eq!(parse("40000-40100"), Some(ports(40000, 40100)));
+        assert_eq!(parse(" 40000 - 40100 "), Some(ports(40000, 40100)));
+    }
+
+    #[test]
+    fn rejects_malformed_ranges() {
+        assert_eq!(parse("40000"), None);
+        assert_eq!(parse("40000-"), None);
+        assert_eq!(parse("a-b"), None);
+        assert_eq!(parse("40000-70000"), None);
+    }
+
+    #[test]
+    fn contains_both_ends() {
+        let range = ports(40000, 40002);
+        assert!(range.contains(40000));
+        assert!(range.contains(40002));
+        assert!(!range.contains(39999));
+        assert!(!range.contains(40003));
+    }
+
+    #[test]
+    fn validates_ranges() {
+        assert!(validate(None).is_ok());
+        assert!(validate(Some(ports(40000, 40000))).is_ok());
+        assert!(validate(Some(ports(40100, 40000))).is_err());
+        assert!(validate(Some(ports(80, 8080))).is_err());
+    }
+}
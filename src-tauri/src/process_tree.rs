@@ -0,0 +1,138 @@
+use std::collections::HashSet;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::warn;
+use sysinfo::{Pid, ProcessStatus, ProcessesToUpdate, Signal, System};
+
+/// How long processes get to exit after SIGTERM before the SIGKILL sweep.
+const TERMINATE_TIMEOUT: Duration = Duration::from_secs(5);
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Collect `root` and all of its descendants, with their start times so a
+/// recycled pid is never mistaken for one of ours later.
+fn collect_tree(system: &System, root: Pid) -> Vec<(Pid, u64)> {
+    let mut tree = Vec::new();
+    let mut seen = HashSet::new();
+    let mut queue = vec![root];
+    while let Some(pid) = queue.pop() {
+        if !seen.insert(pid) {
+            continue;
+        }
+        if let Some(process) = system.process(pid) {
+            tree.push((pid, process.start_time()));
+        }
+        queue.extend(
+            system
+                .processes()
+                .iter()
+                .filter(|(_, process)| process.parent() == Some(pid))
+                .map(|(child, _)| *child),
+        );
+    }
+    tree
+}
+
+/// Return the processes from `tree` that are still running.
+fn still_running(system: &mut System, tree: &[(Pid, u64)]) -> Vec<(Pid, u64)> {
+    let pids: Vec<Pid> = tree.iter().map(|(pid, _)| *pid).collect();
+    system.refresh_processes(ProcessesToUpdate::Some(&pids), true);
+    tree.iter()
+        .filter(|(pid, start_time)| {
+            system.process(*pid).is_some_and(|process| {
+                process.start_time() == *start_time && process.status() != ProcessStatus::Zombie
+            })
+        })
+        .copied()
+        .collect()
+}
+
+/// Terminate `root_pid` along with every process it spawned (VZ helpers and
+/// the like), which would otherwise be orphaned when only the sidecar dies.
+///
+/// The tree is snapshotted before signalling, everything gets SIGTERM, and
+/// whatever is left after `TERMINATE_TIMEOUT` is killed outright.
+pub fn kill_tree(root_pid: u32) {
+    let mut system = System::new();
+    system.refresh_processes(ProcessesToUpdate::All, true);
+    let tree = collect_tree(&system, Pid::from_u32(root_pid));
+
+    // Signal children first so the server doesn't respawn them while exiting.
+    for (pid, _) in tree.iter().rev() {
+        if let Some(process) = system.process(*pid) {
+            // Not every platform supports SIGTERM; those go straight to the sweep.
+            let _ = process.kill_with(Signal::Term);
+        }
+    }
+
+    let deadline = Instant::now() + TERMINATE_TIMEOUT;
+    let mut remaining = still_running(&mut system, &tree);
+    while !remaining.is_empty() && Instant::now() < deadline {
+        thread::sleep(POLL_INTERVAL);
+        remaining = still_running(&mut system, &remaining);
+    }
+
+    for (pid, _) in &remaining {
+        if let Some(process) = system.process(*pid) {
+            warn!("Process {} did not exit after SIGTERM, killing it", pid);
+            process.kill();
+        }
+    }
+}
+
+/// Place the sidecar in a Job Object that kills every process in it when the
+/// last handle closes, so children die with discobot even if it crashes.
+#[cfg(windows)]
+pub fn contain(pid: u32) -> Result<(), String> {
+    use std::sync::OnceLock;
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation,
+        SetInformationJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+        JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+    };
+    use windows_sys::Win32::System::Threading::{
+        OpenProcess, PROCESS_SET_QUOTA, PROCESS_TERMINATE,
+    };
+
+    // The job handle is intentionally never closed: it lives as long as the
+    // app, and the OS closing it on exit is what tears the children down.
+    static JOB: OnceLock<usize> = OnceLock::new();
+
+    let job = match JOB.get() {
+        Some(job) => *job as HANDLE,
+        None => unsafe {
+            let job = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+            if job.is_null() {
+                return Err("Failed to create job object".to_string());
+            }
+            let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+            info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+            let ok = SetInformationJobObject(
+                job,
+                JobObjectExtendedLimitInformation,
+                &info as *const _ as *const std::ffi::c_void,
+                std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+            );
+            if ok == 0 {
+                CloseHandle(job);
+                return Err("Failed to configure job object".to_string());
+            }
+            *JOB.get_or_init(|| job as usize) as HANDLE
+        },
+    };
+
+    unsafe {
+        let process = OpenProcess(PROCESS_SET_QUOTA | PROCESS_TERMINATE, 0, pid);
+        if process.is_null() {
+            return Err(format!("Failed to open process {}", pid));
+        }
+        let ok = AssignProcessToJobObject(job, process);
+        CloseHandle(process);
+        if ok == 0 {
+            return Err(format!("Failed to assign process {} to job object", pid));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,195 @@
+use crate::settings::{ProxyMode, ProxySettings};
+
+/// Proxy URLs to hand to the server.
+struct Proxies {
+    http: Option<String>,
+    https: Option<String>,
+    no_proxy: Option<String>,
+}
+
+/// Proxy environment variables for the sidecar, according to `settings`.
+///
+/// GUI apps usually aren't launched from a shell that exports `HTTP_PROXY`,
+/// so in `System` mode the OS proxy configuration is read at every spawn,
+/// falling back to the app's environment. Both upper- and lowercase names are
+/// set since tools disagree on which one they read.
+pub fn env(settings: &ProxySettings) -> Vec<(&'static str, String)> {
+    let proxies = match settings.mode {
+        ProxyMode::System => detect().unwrap_or_else(from_env),
+        ProxyMode::Manual => Proxies {
+            http: settings.http.clone(),
+            https: settings.https.clone().or_else(|| settings.http.clone()),
+            no_proxy: settings.no_proxy.clone(),
+        },
+        // Empty values override anything inherited from the environment
+        ProxyMode::Off => {
+            return vec![
+                ("HTTP_PROXY", String::new()),
+                ("http_proxy", String::new()),
+                ("HTTPS_PROXY", String::new()),
+                ("https_proxy", String::new()),
+                ("ALL_PROXY", String::new()),
+                ("all_proxy", String::new()),
+                ("NO_PROXY", "*".to_string()),
+                ("no_proxy", "*".to_string()),
+            ];
+        }
+    };
+
+    let mut vars = Vec::new();
+    for (names, value) in [
+        (["HTTP_PROXY", "http_proxy"], proxies.http),
+        (["HTTPS_PROXY", "https_proxy"], proxies.https),
+        (["NO_PROXY", "no_proxy"], proxies.no_proxy),
+    ] {
+        if let Some(value) = value.filter(|value| !value.is_empty()) {
+            for name in names {
+                vars.push((name, value.clone()));
+            }
+        }
+    }
+    vars
+}
+
+fn from_env() -> Proxies {
+    let var = |upper: &str, lower: &str| {
+        std::env::var(upper)
+            .or_else(|_| std::env::var(lower))
+            .ok()
+            .filter(|value| !value.is_empty())
+    };
+    Proxies {
+        http: var("HTTP_PROXY", "http_proxy"),
+        https: var("HTTPS_PROXY", "https_proxy"),
+        no_proxy: var("NO_PROXY", "no_proxy"),
+    }
+}
+
+/// Add a scheme to a bare `host:port` so every client parses it the same way.
+#[cfg(any(target_os = "macos", windows))]
+fn proxy_url(address: &str) -> String {
+    if address.contains("://") {
+        address.to_string()
+    } else {
+        format!("http://{}", address)
+    }
+}
+
+/// Read the proxies from System Settings via `scutil --proxy`.
+#[cfg(target_os = "macos")]
+fn detect() -> Option<Proxies> {
+    let output = std::process::Command::new("scutil")
+        .arg("--proxy")
+        .output()
+        .ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+
+    let mut values = std::collections::HashMap::new();
+    let mut exceptions = Vec::new();
+    let mut in_exceptions = false;
+    for line in text.lines() {
+        let line = line.trim();
+        if in_exceptions {
+            if line == "}" {
+                in_exceptions = false;
+            } else if let Some((_, host)) = line.split_once(" : ") {
+                exceptions.push(host.to_string());
+            }
+            continue;
+        }
+        if line.starts_with("ExceptionsList :") {
+            in_exceptions = true;
+        } else if let Some((key, value)) = line.split_once(" : ") {
+            values.insert(key.to_string(), value.to_string());
+        }
+    }
+
+    let proxy = |prefix: &str| {
+        if values.get(&format!("{}Enable", prefix))? != "1" {
+            return None;
+        }
+        let host = values.get(&format!("{}Proxy", prefix))?;
+        let port = values.get(&format!("{}Port", prefix))?;
+        Some(proxy_url(&format!("{}:{}", host, port)))
+    };
+    let proxies = Proxies {
+        http: proxy("HTTP"),
+        https: proxy("HTTPS"),
+        no_proxy: (!exceptions.is_empty()).then(|| exceptions.join(",")),
+    };
+    (proxies.http.is_some() || proxies.https.is_some()).then_some(proxies)
+}
+
+/// Read the user's manual proxy through WinHTTP, which reports what the
+/// Windows proxy settings page shows. Automatic configuration scripts
+/// aren't followed.
+#[cfg(windows)]
+fn detect() -> Option<Proxies> {
+    use windows_sys::Win32::Networking::WinHttp::{
+        WinHttpGetIEProxyConfigForCurrentUser, WINHTTP_CURRENT_USER_IE_PROXY_CONFIG,
+    };
+
+    let mut config = WINHTTP_CURRENT_USER_IE_PROXY_CONFIG::default();
+    // SAFETY: `config` is a valid out-pointer for the call.
+    if unsafe { WinHttpGetIEProxyConfigForCurrentUser(&mut config) } == 0 {
+        return None;
+    }
+    // Every string must be freed, so take them all before looking at any
+    let _ = take_wide_string(config.lpszAutoConfigUrl);
+    let server = take_wide_string(config.lpszProxy);
+    let bypass = take_wide_string(config.lpszProxyBypass);
+
+    // Set only while a manual proxy is on: either `host:port` for every
+    // protocol or `http=host:port;https=...`
+    let server = server.filter(|server| !server.trim().is_empty())?;
+    let (http, https) = if server.contains('=') {
+        let protocol = |name: &str| {
+            server.split(';').find_map(|entry| {
+                let (key, address) = entry.split_once('=')?;
+                (key.trim() == name).then(|| proxy_url(address.trim()))
+            })
+        };
+        (protocol("http"), protocol("https"))
+    } else {
+        (Some(proxy_url(&server)), Some(proxy_url(&server)))
+    };
+    // `<local>` means "bypass for plain hostnames", which NO_PROXY can't express
+    let no_proxy = bypass.map(|bypass| {
+        bypass
+            .split(|c: char| c == ';' || c.is_whitespace())
+            .filter(|entry| !entry.is_empty() && *entry != "<local>")
+            .collect::<Vec<_>>()
+            .join(",")
+    });
+
+    Some(Proxies {
+        http,
+        https,
+        no_proxy,
+    })
+}
+
+/// Copy a string WinHTTP allocated and free it.
+#[cfg(windows)]
+fn take_wide_string(string: windows_sys::core::PWSTR) -> Option<String> {
+    use windows_sys::Win32::Foundation::GlobalFree;
+
+    if string.is_null() {
+        return None;
+    }
+    // SAFETY: WinHTTP returns NUL-terminated strings from GlobalAlloc, which
+    // aren't used again after being freed here.
+    unsafe {
+        let len = (0..).take_while(|&i| *string.add(i) != 0).count();
+        let value = String::from_utf16_lossy(std::slice::from_raw_parts(string, len));
+        GlobalFree(string.cast());
+        Some(value)
+    }
+}
+
+/// Desktop environments differ too much to query; Linux users are expected to
+/// export `http_proxy` and friends, which `from_env` picks up.
+#[cfg(not(any(target_os = "macos", windows)))]
+fn detect() -> Option<Proxies> {
+    None
+}
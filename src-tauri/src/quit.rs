@@ -0,0 +1,148 @@
+use std::sync::Mutex;
+
+use log::warn;
+use tauri::Manager;
+use tauri_plugin_dialog::{
+    DialogExt, MessageDialogButtons, MessageDialogKind, MessageDialogResult,
+};
+
+use crate::settings::{self, Settings};
+use crate::{health, shutdown_server, ServerState};
+
+const QUIT_BUTTON: &str = "Quit";
+const ALWAYS_QUIT_BUTTON: &str = "Quit, Don't Ask Again";
+const CANCEL_BUTTON: &str = "Cancel";
+
+/// Quit the app, first asking for confirmation if the server has agent runs
+/// in progress that stopping it would interrupt. Every path that quits the
+/// app should go through here.
+pub fn request_quit(app: &tauri::AppHandle) {
+    quit(app, false);
+}
+
+/// Route an exit the app didn't ask for, such as Cmd+Q or the app menu's
+/// Quit, through `request_quit` so the server is stopped first. Exits with a
+/// code come from `AppHandle::exit` or `restart` once that's done.
+pub fn on_exit_requested(app: &tauri::AppHandle, api: &tauri::ExitRequestApi, code: Option<i32>) {
+    if code.is_some()
+        || app
+            .state::<Mutex<ServerState>>()
+            .lock()
+            .unwrap()
+            .shutting_down
+    {
+        return;
+    }
+    api.prevent_exit();
+    request_quit(app);
+}
+
+/// `request_quit` for the tray's Quit item, which also asks first when
+/// `Settings::confirm_quit` is on, since it's easy to hit by accident.
+pub fn request_tray_quit(app: &tauri::AppHandle) {
+    quit(app, true);
+}
+
+fn quit(app: &tauri::AppHandle, from_tray: bool) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let ask = from_tray && app.state::<Mutex<Settings>>().lock().unwrap().confirm_quit;
+        // Once confirmed there's no need to ask again about running tasks
+        let confirmed = if ask {
+            confirm_stop(&app).await
+        } else {
+            confirm_quit(&app).await
+        };
+        if !confirmed {
+            return;
+        }
+        let handle = app.clone();
+        let _ = tauri::async_runtime::spawn_blocking(move || shutdown_server(&handle)).await;
+        app.exit(0);
+    });
+}
+
+async fn confirm_quit(app: &tauri::AppHandle) -> bool {
+    let confirm = app
+        .state::<Mutex<Settings>>()
+        .lock()
+        .unwrap()
+        .confirm_quit_with_active_tasks;
+    let (endpoint, secret) = {
+        let state = app.state::<Mutex<ServerState>>();
+        let state = state.lock().unwrap();
+        // Quitting doesn't stop a server we didn't start
+        if !confirm || state.external || state.process.is_none() {
+            return true;
+        }
+        (state.endpoint(), state.secret.clone())
+    };
+
+    let count = match health::active_tasks(&endpoint, secret.expose()).await {
+        Some(count) if count > 0 => count,
+        _ => return true,
+    };
+
+    let message = if count == 1 {
+        "1 agent task is still running and will be interrupted if you quit.".to_string()
+    } else {
+        format!(
+            "{} agent tasks are still running and will be interrupted if you quit.",
+            count
+        )
+    };
+    let handle = app.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        handle
+            .dialog()
+            .message(message)
+            .title("Quit Discobot?")
+            .kind(MessageDialogKind::Warning)
+            .buttons(MessageDialogButtons::YesNoCancelCustom(
+                QUIT_BUTTON.to_string(),
+                ALWAYS_QUIT_BUTTON.to_string(),
+                CANCEL_BUTTON.to_string(),
+            ))
+            .blocking_show_with_result()
+    })
+    .await;
+
+    match result {
+        Ok(MessageDialogResult::Custom(button)) if button == QUIT_BUTTON => true,
+        Ok(MessageDialogResult::Custom(button)) if button == ALWAYS_QUIT_BUTTON => {
+            stop_asking(app);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Ask before stopping the server, whether or not it's busy.
+async fn confirm_stop(app: &tauri::AppHandle) -> bool {
+    let handle = app.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        handle
+            .dialog()
+            .message("Quit Discobot? The server and any running VMs will stop.")
+            .title("Quit Discobot?")
+            .kind(MessageDialogKind::Warning)
+            .buttons(MessageDialogButtons::OkCancelCustom(
+                QUIT_BUTTON.to_string(),
+                CANCEL_BUTTON.to_string(),
+            ))
+            .blocking_show_with_result()
+    })
+    .await;
+    matches!(result, Ok(MessageDialogResult::Custom(button)) if button == QUIT_BUTTON)
+}
+
+fn stop_asking(app: &tauri::AppHandle) {
+    let state = app.state::<Mutex<Settings>>();
+    let mut current = state.lock().unwrap();
+    let mut updated = current.clone();
+    updated.confirm_quit_with_active_tasks = false;
+    match settings::save(&updated) {
+        Ok(()) => *current = updated,
+        Err(e) => warn!("Failed to save quit confirmation preference: {}", e),
+    }
+}
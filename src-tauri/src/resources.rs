@@ -0,0 +1,126 @@
+use std::sync::Mutex;
+
+use log::warn;
+use serde::Serialize;
+use sysinfo::System;
+use tauri::Manager;
+
+use crate::settings::{self, Settings};
+
+/// Below this the sandbox VM doesn't boot reliably.
+const MIN_MEMORY_MB: u64 = 1024;
+const MIN_CPU_COUNT: u64 = 1;
+
+#[derive(Serialize)]
+pub struct HostResources {
+    pub memory_mb: u64,
+    pub cpu_count: u64,
+}
+
+fn host_resources() -> HostResources {
+    let mut system = System::new();
+    system.refresh_memory();
+    HostResources {
+        memory_mb: system.total_memory() / (1024 * 1024),
+        cpu_count: std::thread::available_parallelism()
+            .map(|count| count.get() as u64)
+            .unwrap_or(1),
+    }
+}
+
+/// The configured VM limits and the ranges they may be set within. `None`
+/// leaves the choice to the server (half the memory and all CPUs).
+#[derive(Serialize)]
+pub struct VmLimits {
+    memory_mb: Option<u32>,
+    cpu_count: Option<u32>,
+    min_memory_mb: u64,
+    max_memory_mb: u64,
+    min_cpu_count: u64,
+    max_cpu_count: u64,
+}
+
+fn validate(memory_mb: Option<u32>, cpu_count: Option<u32>) -> Result<(), String> {
+    let host = host_resources();
+    if let Some(memory_mb) = memory_mb.map(u64::from) {
+        if memory_mb < MIN_MEMORY_MB || memory_mb > host.memory_mb {
+            return Err(format!(
+                "VM memory must be between {} and {} MB",
+                MIN_MEMORY_MB, host.memory_mb
+            ));
+        }
+    }
+    if let Some(cpu_count) = cpu_count.map(u64::from) {
+        if cpu_count < MIN_CPU_COUNT || cpu_count > host.cpu_count {
+            return Err(format!(
+                "VM CPU count must be between {} and {}",
+                MIN_CPU_COUNT, host.cpu_count
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// `VZ_MEMORY_MB` and `VZ_CPU_COUNT` for the sidecar. Limits that are out of
+/// range, e.g. after moving settings to a smaller machine, are left out with
+/// a warning so the server falls back to its defaults.
+pub fn vm_env(settings: &Settings) -> Vec<(&'static str, String)> {
+    let mut vars = Vec::new();
+    if let Some(memory_mb) = settings.vm_memory_mb {
+        match validate(Some(memory_mb), None) {
+            Ok(()) => vars.push(("VZ_MEMORY_MB", memory_mb.to_string())),
+            Err(e) => warn!("Ignoring VM memory limit: {}", e),
+        }
+    }
+    if let Some(cpu_count) = settings.vm_cpu_count {
+        match validate(None, Some(cpu_count)) {
+            Ok(()) => vars.push(("VZ_CPU_COUNT", cpu_count.to_string())),
+            Err(e) => warn!("Ignoring VM CPU limit: {}", e),
+        }
+    }
+    vars
+}
+
+#[tauri::command]
+pub fn get_host_resources() -> HostResources {
+    host_resources()
+}
+
+#[tauri::command]
+pub fn get_vm_limits(state: tauri::State<'_, Mutex<Settings>>) -> VmLimits {
+    let host = host_resources();
+    let settings = state.lock().unwrap();
+    VmLimits {
+        memory_mb: settings.vm_memory_mb,
+        cpu_count: settings.vm_cpu_count,
+        min_memory_mb: MIN_MEMORY_MB,
+        max_memory_mb: host.memory_mb,
+        min_cpu_count: MIN_CPU_COUNT,
+        max_cpu_count: host.cpu_count,
+    }
+}
+
+/// Change the VM limits, which take effect when the server restarts.
+#[tauri::command]
+pub fn set_vm_limits(
+    app: tauri::AppHandle,
+    memory_mb: Option<u32>,
+    cpu_count: Option<u32>,
+) -> Result<(), String> {
+    validate(memory_mb, cpu_count)?;
+    {
+        let state = app.state::<Mutex<Settings>>();
+        let mut current = state.lock().unwrap();
+        if current.vm_memory_mb == memory_mb && current.vm_cpu_count == cpu_count {
+            return Ok(());
+        }
+        let mut updated = current.clone();
+        updated.vm_memory_mb = memory_mb;
+        updated.vm_cpu_count = cpu_count;
+        settings::save(&updated)?;
+        *current = updated;
+    }
+
+    crate::mark_restart_required(&app);
+    Ok(())
+}
@@ -0,0 +1,184 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::Mutex;
+
+use log::warn;
+use rand::rngs::OsRng;
+use rand::TryRngCore;
+use zeroize::Zeroize;
+
+/// Length of generated secrets, from 32 (the default) to 64.
+const LENGTH_ENV: &str = "DISCOBOT_SECRET_LENGTH";
+const DEFAULT_LENGTH: usize = 32;
+const MAX_LENGTH: usize = 64;
+
+/// 64 URL- and cookie-safe characters, so a random byte maps onto one with a
+/// 6-bit mask and every character is equally likely.
+const CHARSET: &[u8; 64] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789-_";
+
+/// The server's API secret, which guards local code execution. Wiped from
+/// memory when dropped and redacted from `{:?}` output; `expose` gives the
+/// value where it's actually needed.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Secret(String);
+
+fn length() -> usize {
+    match std::env::var(LENGTH_ENV) {
+        Ok(value) => match value.parse() {
+            Ok(length) if (DEFAULT_LENGTH..=MAX_LENGTH).contains(&length) => length,
+            _ => {
+                warn!(
+                    "Ignoring {}={}, expected {} to {}",
+                    LENGTH_ENV, value, DEFAULT_LENGTH, MAX_LENGTH
+                );
+                DEFAULT_LENGTH
+            }
+        },
+        Err(_) => DEFAULT_LENGTH,
+    }
+}
+
+impl Secret {
+    /// A new random secret drawn from the OS's secure random number generator.
+    pub fn generate() -> Self {
+        let mut bytes = vec![0u8; length()];
+        OsRng
+            .try_fill_bytes(&mut bytes)
+            .expect("Failed to read from the OS random number generator");
+        let secret = bytes
+            .iter()
+            .map(|byte| char::from(CHARSET[usize::from(byte & 0x3f)]))
+            .collect();
+        bytes.zeroize();
+        Self(secret)
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<String> for Secret {
+    fn from(secret: String) -> Self {
+        Self(secret)
+    }
+}
+
+impl Drop for Secret {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// Replaces secrets in text bound for disk.
+const REDACTED: &str = "[REDACTED]";
+
+/// Every secret used this session, the current one and any rotated out, so
+/// output the sidecar echoes can be scrubbed of them before it's written to
+/// server.log or shown. Anything else that writes server output or
+/// diagnostics should go through `redact` too.
+#[derive(Default)]
+pub struct Redactor(Mutex<Vec<Secret>>);
+
+impl Redactor {
+    pub fn add(&self, secret: &Secret) {
+        let mut secrets = self.0.lock().unwrap();
+        if !secret.is_empty() && !secrets.contains(secret) {
+            secrets.push(secret.clone());
+        }
+    }
+
+    /// `text` with every secret replaced, including one a line break splits
+    /// in two when `text` holds both lines.
+    pub fn redact<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let secrets = self.0.lock().unwrap();
+        let mut text = Cow::Borrowed(text);
+        for secret in secrets.iter() {
+            let secret = secret.expose();
+            if text.contains(secret) {
+                text = Cow::Owned(text.replace(secret, REDACTED));
+            }
+            if !text.contains('\n') {
+                continue;
+            }
+            for split in (1..secret.len()).filter(|&split| secret.is_char_boundary(split)) {
+                for newline in ["\r\n", "\n"] {
+                    let wrapped = format!("{}{}{}", &secret[..split], newline, &secret[split..]);
+                    if text.contains(&wrapped) {
+                        text = Cow::Owned(text.replace(&wrapped, REDACTED));
+                    }
+                }
+            }
+        }
+        text
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(<redacted>)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_secret_has_the_default_length() {
+        assert_eq!(Secret::generate().expose().len(), DEFAULT_LENGTH);
+    }
+
+    #[test]
+    fn generated_secret_only_uses_the_charset() {
+        let secret = Secret::generate();
+        assert!(secret.expose().bytes().all(|byte| CHARSET.contains(&byte)));
+    }
+
+    #[test]
+    fn consecutive_secrets_differ() {
+        assert_ne!(Secret::generate(), Secret::generate());
+    }
+
+    fn redactor(secret: &str) -> Redactor {
+        let redactor = Redactor::default();
+        redactor.add(&Secret::from(secret.to_string()));
+        redactor
+    }
+
+    #[test]
+    fn redacts_the_secret_in_a_log_line() {
+        let redactor = redactor("s3cr3t-value");
+        let line = "2026/10/16 12:00:00 auth: Bearer s3cr3t-value rejected";
+        assert_eq!(
+            redactor.redact(line),
+            "2026/10/16 12:00:00 auth: Bearer [REDACTED] rejected"
+        );
+    }
+
+    #[test]
+    fn leaves_lines_without_a_secret_alone() {
+        let redactor = redactor("s3cr3t-value");
+        assert!(matches!(
+            redactor.redact("Server starting on port 3001"),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn redacts_a_secret_split_across_two_lines() {
+        let redactor = redactor("s3cr3t-value");
+        assert_eq!(
+            redactor.redact("token=s3cr3t-\nvalue end"),
+            "token=[REDACTED] end"
+        );
+        assert_eq!(
+            redactor.redact("token=s3c\r\nr3t-value end"),
+            "token=[REDACTED] end"
+        );
+    }
+}
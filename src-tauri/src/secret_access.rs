@@ -0,0 +1,174 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use log::{info, warn};
+use tauri::webview::{PageLoadEvent, PageLoadPayload};
+use tauri::{Manager, Webview};
+
+use crate::settings::Settings;
+use crate::ServerState;
+
+/// The only window allowed the secret. The capability in `capabilities/`
+/// already keeps other windows from invoking the command at all.
+const SECRET_WINDOW: &str = "main";
+
+/// Gates `get_server_secret` so the secret is handed out once per frontend:
+/// the main window's page fetches it as it starts, and anything that tries
+/// after it is refused until the page reloads or `allow_secret_refetch`.
+pub struct SecretAccess {
+    /// Set while a fetch is allowed.
+    armed: AtomicBool,
+}
+
+impl Default for SecretAccess {
+    fn default() -> Self {
+        Self {
+            armed: AtomicBool::new(true),
+        }
+    }
+}
+
+impl SecretAccess {
+    /// A reload starts a frontend without the secret.
+    pub fn on_page_load(&self, webview: &Webview, payload: &PageLoadPayload<'_>) {
+        rearm_on_load(webview.label(), payload.event(), &self.armed);
+    }
+}
+
+fn rearm_on_load(label: &str, event: PageLoadEvent, armed: &AtomicBool) {
+    if label == SECRET_WINDOW && event == PageLoadEvent::Started {
+        armed.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Let the frontend fetch the secret once more, after it changed.
+pub fn allow_secret_refetch(app: &tauri::AppHandle) {
+    app.state::<SecretAccess>()
+        .armed
+        .store(true, Ordering::SeqCst);
+}
+
+/// Why `get_server_secret` refused a window.
+#[derive(Debug, PartialEq)]
+enum Refusal {
+    LegacyAccessOff,
+    WrongWindow,
+    AlreadyFetched,
+}
+
+impl Refusal {
+    /// Appended to the warning logged for the refusal.
+    fn reason(&self) -> &'static str {
+        match self {
+            Self::LegacyAccessOff => ", legacy secret access is off",
+            Self::WrongWindow => "",
+            Self::AlreadyFetched => ", it was already fetched",
+        }
+    }
+
+    fn error(&self) -> String {
+        match self {
+            Self::LegacyAccessOff => {
+                "The server secret isn't available; use discobot-api:// instead"
+            }
+            Self::WrongWindow => "The server secret is only available to the main window",
+            Self::AlreadyFetched => "The server secret has already been fetched",
+        }
+        .to_string()
+    }
+}
+
+/// Whether the window `label` may have the secret, using up `armed` if so.
+fn check_access(label: &str, legacy_access: bool, armed: &AtomicBool) -> Result<(), Refusal> {
+    if !legacy_access {
+        return Err(Refusal::LegacyAccessOff);
+    }
+    if label != SECRET_WINDOW {
+        return Err(Refusal::WrongWindow);
+    }
+    if !armed.swap(false, Ordering::SeqCst) {
+        return Err(Refusal::AlreadyFetched);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_server_secret(
+    webview: Webview,
+    access: tauri::State<'_, SecretAccess>,
+    settings: tauri::State<'_, Mutex<Settings>>,
+    state: tauri::State<'_, Mutex<ServerState>>,
+) -> Result<String, String> {
+    let label = webview.label();
+    let legacy_access = settings.lock().unwrap().legacy_secret_access;
+    if let Err(refusal) = check_access(label, legacy_access, &access.armed) {
+        warn!(
+            "Refused the server secret to window \"{}\"{}",
+            label,
+            refusal.reason()
+        );
+        return Err(refusal.error());
+    }
+    info!("Server secret fetched by window \"{}\"", label);
+    Ok(state.lock().unwrap().secret.expose().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn main_window_gets_the_secret_once() {
+        let armed = AtomicBool::new(true);
+        assert_eq!(check_access("main", true, &armed), Ok(()));
+        assert_eq!(
+            check_access("main", true, &armed),
+            Err(Refusal::AlreadyFetched)
+        );
+    }
+
+    #[test]
+    fn other_windows_are_refused_without_using_up_the_fetch() {
+        let armed = AtomicBool::new(true);
+        assert_eq!(
+            check_access("log-viewer", true, &armed),
+            Err(Refusal::WrongWindow)
+        );
+        assert_eq!(check_access("main", true, &armed), Ok(()));
+    }
+
+    #[test]
+    fn refetch_is_allowed_once_rearmed() {
+        let armed = AtomicBool::new(false);
+        assert_eq!(
+            check_access("main", true, &armed),
+            Err(Refusal::AlreadyFetched)
+        );
+        armed.store(true, Ordering::SeqCst);
+        assert_eq!(check_access("main", true, &armed), Ok(()));
+    }
+
+    #[test]
+    fn reloading_the_main_window_allows_another_fetch() {
+        let armed = AtomicBool::new(true);
+        assert_eq!(check_access("main", true, &armed), Ok(()));
+        rearm_on_load("main", PageLoadEvent::Finished, &armed);
+        rearm_on_load("log-viewer", PageLoadEvent::Started, &armed);
+        assert_eq!(
+            check_access("main", true, &armed),
+            Err(Refusal::AlreadyFetched)
+        );
+        rearm_on_load("main", PageLoadEvent::Started, &armed);
+        assert_eq!(check_access("main", true, &armed), Ok(()));
+    }
+
+    #[test]
+    fn everything_is_refused_without_legacy_access() {
+        let armed = AtomicBool::new(true);
+        assert_eq!(
+            check_access("main", false, &armed),
+            Err(Refusal::LegacyAccessOff)
+        );
+        assert!(armed.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,347 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use keyring::Entry;
+use log::{info, warn};
+use tauri::{Emitter, Manager};
+
+use crate::instance_lock::InstanceLock;
+use crate::secret::{Redactor, Secret};
+use crate::secret_access;
+use crate::settings::Settings;
+use crate::{respawn_server, ServerState, ServerStatus, READY_TIMEOUT};
+
+/// Keychain item holding the secret.
+const SERVICE: &str = "discobot";
+const ACCOUNT: &str = "server-secret";
+
+/// Event emitted when the secret is rotated, so the frontend fetches the new
+/// one with `get_server_secret`.
+const SECRET_ROTATED_EVENT: &str = "secret-rotated";
+
+/// How often `rotate_secret` checks whether the server is back up.
+const ROTATE_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Where the server secret is kept between launches.
+pub trait SecretStore: Send {
+    fn load(&self) -> Result<Option<Secret>, String>;
+    fn save(&self, secret: &Secret) -> Result<(), String>;
+    fn clear(&self) -> Result<(), String>;
+}
+
+/// Keeps nothing, so every launch gets a new secret. The default.
+pub struct Ephemeral;
+
+impl SecretStore for Ephemeral {
+    fn load(&self) -> Result<Option<Secret>, String> {
+        Ok(None)
+    }
+
+    fn save(&self, _: &Secret) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// The platform keychain: Keychain Services on macOS, the Secret Service on
+/// Linux and the Credential Manager on Windows.
+pub struct Keychain;
+
+impl Keychain {
+    fn entry() -> Result<Entry, String> {
+        Entry::new(SERVICE, ACCOUNT).map_err(|e| format!("Failed to open the keychain: {}", e))
+    }
+}
+
+impl SecretStore for Keychain {
+    fn load(&self) -> Result<Option<Secret>, String> {
+        match Self::entry()?.get_password() {
+            Ok(secret) => Ok(Some(Secret::from(secret))),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(format!(
+                "Failed to read the secret from the keychain: {}",
+                e
+            )),
+        }
+    }
+
+    fn save(&self, secret: &Secret) -> Result<(), String> {
+        Self::entry()?
+            .set_password(secret.expose())
+            .map_err(|e| format!("Failed to save the secret to the keychain: {}", e))
+    }
+
+    fn clear(&self) -> Result<(), String> {
+        match Self::entry()?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(format!(
+                "Failed to remove the secret from the keychain: {}",
+                e
+            )),
+        }
+    }
+}
+
+/// The store `Settings::persist_secret` selects.
+pub fn store(persist: bool) -> Box<dyn SecretStore> {
+    if persist {
+        Box::new(Keychain)
+    } else {
+        Box::new(Ephemeral)
+    }
+}
+
+/// The secret for this launch: the stored one if there is one, otherwise a
+/// new one, which is stored for next time. If the store can't be used, the
+/// secret only lasts this launch.
+pub fn load_or_generate(store: &dyn SecretStore) -> Secret {
+    match store.load() {
+        Ok(Some(secret)) if !secret.is_empty() => return secret,
+        Ok(_) => {}
+        Err(e) => {
+            warn!("{}, using a new secret for this launch", e);
+            return Secret::generate();
+        }
+    }
+    let secret = Secret::generate();
+    if let Err(e) = store.save(&secret) {
+        warn!("{}, the secret will change next launch", e);
+    }
+    secret
+}
+
+/// `load_or_generate` with the store `persist` selects, on a thread of its
+/// own: keyring's Secret Service backend runs on tokio and can deadlock when
+/// called from the main thread.
+pub fn launch_secret(persist: bool) -> Secret {
+    std::thread::spawn(move || load_or_generate(&*store(persist)))
+        .join()
+        .unwrap_or_else(|_| {
+            warn!("Failed to load the secret, using a new secret for this launch");
+            Secret::generate()
+        })
+}
+
+/// Keep a rotated-in secret for next launch. If it can't be saved, the old
+/// one stays stored and the secret changes again next launch.
+fn save_rotated(store: &dyn SecretStore, secret: &Secret) {
+    if let Err(e) = store.save(secret) {
+        warn!("{}, the secret will change next launch", e);
+    }
+}
+
+/// Store or forget the current secret after `Settings::persist_secret` is
+/// toggled. Runs in the background since the keychain may prompt.
+pub fn apply_setting(app: &tauri::AppHandle, persist: bool) {
+    let secret = {
+        let state = app.state::<Mutex<ServerState>>();
+        let state = state.lock().unwrap();
+        // An external server's secret isn't ours to keep
+        if state.external || state.secret.is_empty() {
+            return;
+        }
+        state.secret.clone()
+    };
+    tauri::async_runtime::spawn_blocking(move || {
+        let result = if persist {
+            Keychain.save(&secret)
+        } else {
+            Keychain.clear()
+        };
+        if let Err(e) = result {
+            warn!("{}", e);
+        }
+    });
+}
+
+/// Wait for a restarted sidecar to report ready, up to `READY_TIMEOUT`.
+async fn wait_until_running(app: &tauri::AppHandle) -> bool {
+    let deadline = tokio::time::Instant::now() + READY_TIMEOUT;
+    while tokio::time::Instant::now() < deadline {
+        match app.state::<Mutex<ServerState>>().lock().unwrap().status {
+            ServerStatus::Running { .. } => return true,
+            ServerStatus::Failed { .. } => return false,
+            _ => {}
+        }
+        tokio::time::sleep(ROTATE_POLL_INTERVAL).await;
+    }
+    false
+}
+
+async fn respawn(app: &tauri::AppHandle) -> Result<(), String> {
+    let handle = app.clone();
+    tauri::async_runtime::spawn_blocking(move || respawn_server(&handle))
+        .await
+        .map_err(|e| format!("Failed to restart server: {}", e))
+}
+
+/// Replace the server secret, e.g. because it leaked, by restarting the
+/// server with a new one, which ends every use of the old one. If the server
+/// doesn't come back up, it's restarted with the old secret instead so the
+/// session keeps working. The new secret is stored if `persist_secret` is on.
+#[tauri::command]
+pub async fn rotate_secret(app: tauri::AppHandle) -> Result<(), String> {
+    let secret = Secret::generate();
+    let previous = {
+        let state = app.state::<Mutex<ServerState>>();
+        let mut state = state.lock().unwrap();
+        if state.external {
+            return Err("The server is managed externally".to_string());
+        }
+        if state.process.is_none() {
+            return Err("Server is not running".to_string());
+        }
+        std::mem::replace(&mut state.secret, secret.clone())
+    };
+    app.state::<Redactor>().add(&secret);
+
+    info!("Restarting server to rotate its secret");
+    respawn(&app).await?;
+    if !wait_until_running(&app).await {
+        warn!("Server didn't start with the new secret, restoring the old one");
+        app.state::<Mutex<ServerState>>().lock().unwrap().secret = previous;
+        respawn(&app).await?;
+        return Err(
+            "The server failed to start with the new secret; the old secret is still in use"
+                .to_string(),
+        );
+    }
+
+    app.state::<InstanceLock>().update_secret(&secret);
+    let persist = app
+        .state::<Mutex<Settings>>()
+        .lock()
+        .unwrap()
+        .persist_secret;
+    // The keychain may block, and prompt
+    let _ =
+        tauri::async_runtime::spawn_blocking(move || save_rotated(&*store(persist), &secret)).await;
+    secret_access::allow_secret_refetch(&app);
+    let _ = app.emit(SECRET_ROTATED_EVENT, ());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An in-memory store that can be made to fail.
+    #[derive(Default)]
+    struct FakeStore {
+        secret: Mutex<Option<String>>,
+        saves: Mutex<u32>,
+        fail_load: bool,
+        fail_save: bool,
+    }
+
+    impl FakeStore {
+        fn holding(secret: &str) -> Self {
+            Self {
+                secret: Mutex::new(Some(secret.to_string())),
+                ..Self::default()
+            }
+        }
+
+        fn stored(&self) -> Option<String> {
+            self.secret.lock().unwrap().clone()
+        }
+    }
+
+    impl SecretStore for FakeStore {
+        fn load(&self) -> Result<Option<Secret>, String> {
+            if self.fail_load {
+                return Err("load failed".to_string());
+            }
+            Ok(self.stored().map(Secret::from))
+        }
+
+        fn save(&self, secret: &Secret) -> Result<(), String> {
+            if self.fail_save {
+                return Err("save failed".to_string());
+            }
+            *self.saves.lock().unwrap() += 1;
+            *self.secret.lock().unwrap() = Some(secret.expose().to_string());
+            Ok(())
+        }
+
+        fn clear(&self) -> Result<(), String> {
+            *self.secret.lock().unwrap() = None;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn generates_and_stores_a_secret_on_first_launch() {
+        let store = FakeStore::default();
+        let secret = load_or_generate(&store);
+        assert!(!secret.is_empty());
+        assert_eq!(store.stored().as_deref(), Some(secret.expose()));
+        assert_eq!(load_or_generate(&store), secret);
+    }
+
+    #[test]
+    fn reuses_the_stored_secret() {
+        let store = FakeStore::holding("stored-secret");
+        assert_eq!(load_or_generate(&store).expose(), "stored-secret");
+        assert_eq!(*store.saves.lock().unwrap(), 0);
+    }
+
+    #[test]
+    fn replaces_an_empty_stored_secret() {
+        let store = FakeStore::holding("");
+        let secret = load_or_generate(&store);
+        assert!(!secret.is_empty());
+        assert_eq!(store.stored().as_deref(), Some(secret.expose()));
+    }
+
+    #[test]
+    fn falls_back_to_a_new_secret_when_loading_fails() {
+        let store = FakeStore {
+            fail_load: true,
+            ..FakeStore::holding("stored-secret")
+        };
+        let secret = load_or_generate(&store);
+        assert!(!secret.is_empty());
+        assert_ne!(secret.expose(), "stored-secret");
+        assert_eq!(*store.saves.lock().unwrap(), 0);
+    }
+
+    #[test]
+    fn still_returns_a_secret_when_saving_fails() {
+        let store = FakeStore {
+            fail_save: true,
+            ..FakeStore::default()
+        };
+        assert!(!load_or_generate(&store).is_empty());
+        assert_eq!(store.stored(), None);
+    }
+
+    #[test]
+    fn ephemeral_store_gives_a_new_secret_each_launch() {
+        assert_ne!(load_or_generate(&Ephemeral), load_or_generate(&Ephemeral));
+    }
+
+    #[test]
+    fn rotated_secret_is_used_next_launch() {
+        let store = FakeStore::default();
+        let first = load_or_generate(&store);
+        let rotated = Secret::generate();
+        save_rotated(&store, &rotated);
+        let next = load_or_generate(&store);
+        assert_eq!(next, rotated);
+        assert_ne!(next, first);
+    }
+
+    #[test]
+    fn failed_rotation_save_keeps_the_old_secret_stored() {
+        let store = FakeStore {
+            fail_save: true,
+            ..FakeStore::holding("old-secret")
+        };
+        save_rotated(&store, &Secret::generate());
+        assert_eq!(load_or_generate(&store).expose(), "old-secret");
+    }
+}
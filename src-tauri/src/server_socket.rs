@@ -0,0 +1,34 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use log::warn;
+
+use crate::ServerState;
+
+/// Set to `1` to serve the API on a Unix socket, like `Settings::unix_socket`.
+const SOCKET_ENV: &str = "DISCOBOT_SERVER_SOCKET";
+
+/// The socket the sidecar should listen on instead of a TCP port, if socket
+/// mode is on (`setting` or `DISCOBOT_SERVER_SOCKET=1`). Always `None` for
+/// now: chat, project events, the terminal and web previews reach the server
+/// at its localhost port, and `discobot-api://` can't carry their streams,
+/// so socket mode is refused with a warning until they can.
+pub fn resolve(setting: bool) -> Option<PathBuf> {
+    if setting || std::env::var(SOCKET_ENV).is_ok_and(|value| value == "1") {
+        warn!(
+            "Unix socket mode isn't supported until streaming works over it, using a TCP port instead"
+        );
+    }
+    None
+}
+
+/// Path of the socket the server listens on, or `None` in TCP mode.
+#[tauri::command]
+pub fn get_server_socket(state: tauri::State<'_, Mutex<ServerState>>) -> Option<String> {
+    state
+        .lock()
+        .unwrap()
+        .socket
+        .as_ref()
+        .map(|path| path.display().to_string())
+}
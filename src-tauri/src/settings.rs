@@ -0,0 +1,398 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+use tauri::Manager;
+
+use crate::{cors, logs, ports, secret_store, shell_log, sidecar, structured_logs};
+
+/// What closing the main window does.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CloseBehavior {
+    /// Hide to the tray and keep the server running.
+    #[default]
+    Hide,
+    /// Quit the app, stopping the server.
+    Quit,
+    /// Let the frontend ask the user via a `close-requested` event.
+    Ask,
+}
+
+/// How sidecar output is written to server.log.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// `YYYY-MM-DD HH:MM:SS [stream] line`, one per line, with the server's
+    /// JSON records rendered readably if `Settings::render_json_logs` is on.
+    #[default]
+    Text,
+    /// One `{"ts":…,"stream":…,"msg":…}` object per line, for log aggregators.
+    Json,
+}
+
+/// Where the server's proxy configuration comes from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProxyMode {
+    /// The OS proxy settings, read when the server starts.
+    #[default]
+    System,
+    /// The URLs in `ProxySettings`.
+    Manual,
+    /// Connect directly, ignoring any proxy variables in the environment.
+    Off,
+}
+
+/// Proxy the server uses for image and registry downloads.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProxySettings {
+    pub mode: ProxyMode,
+    pub http: Option<String>,
+    /// Defaults to `http` when unset.
+    pub https: Option<String>,
+    /// Comma-separated hosts to reach directly.
+    pub no_proxy: Option<String>,
+}
+
+/// Inclusive range the server and SSH ports are allocated from.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PortRange {
+    pub start: u16,
+    pub end: u16,
+}
+
+/// Optional server features, passed to the sidecar as environment variables.
+/// The defaults match what the app always enabled.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FeatureFlags {
+    /// Prompt suggestions, which send prompts to the suggestion backend.
+    pub suggestions: bool,
+    /// The SSH server for connecting to sessions.
+    pub ssh: bool,
+    /// Running agents directly on the host instead of in a sandbox.
+    pub local_provider: bool,
+    /// A Docker API proxy for debugging sandboxes.
+    pub debug_docker: bool,
+}
+
+impl Default for FeatureFlags {
+    fn default() -> Self {
+        Self {
+            suggestions: true,
+            ssh: true,
+            local_provider: false,
+            debug_docker: false,
+        }
+    }
+}
+
+/// User preferences persisted to `<config_dir>/discobot/settings.json`.
+///
+/// Every field has a default so older or partially written files still load.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Show native notifications when the server fails or crashes.
+    pub notifications_enabled: bool,
+    /// Restart the server automatically if it's unresponsive after the system
+    /// wakes from sleep. When off, the frontend is asked to prompt instead.
+    pub restart_on_wake: bool,
+    /// How often to check that the running server still answers; `None`
+    /// turns the watchdog off.
+    pub health_check_interval_secs: Option<u64>,
+    /// Failed checks in a row after which the server is restarted.
+    pub health_check_failures: u32,
+    /// Times to try spawning the server before giving up, e.g. while
+    /// antivirus software still holds the binary on first launch. Up to 10.
+    pub spawn_attempts: u32,
+    /// Wait before the second attempt, growing with each further one up to
+    /// 30 seconds.
+    pub spawn_retry_delay_ms: u64,
+    /// Custom sandbox VM kernel to boot instead of the bundled one (macOS
+    /// only). `VZ_KERNEL_PATH` takes precedence.
+    pub vz_kernel_path: Option<String>,
+    /// Custom sandbox VM root filesystem image (macOS only).
+    /// `VZ_BASE_DISK_PATH` takes precedence.
+    pub vz_base_disk_path: Option<String>,
+    /// `host:port` of a server to attach to instead of spawning the bundled
+    /// one. `DISCOBOT_EXTERNAL_SERVER` takes precedence. Dev builds always
+    /// attach to the dev server unless `DISCOBOT_SPAWN_SIDECAR=1` is set.
+    pub external_server: Option<String>,
+    /// Port the server should always use (release builds only), tried before
+    /// the one it last ran on. `DISCOBOT_PORT` takes precedence.
+    pub fixed_port: Option<u16>,
+    /// Keep the server and SSH ports within this range rather than anywhere
+    /// the OS picks (release builds only). `DISCOBOT_PORT_RANGE` takes
+    /// precedence.
+    pub port_range: Option<PortRange>,
+    /// Serve the API on a Unix socket instead of a localhost TCP port.
+    /// Currently ignored with a warning, since the streaming endpoints behind
+    /// chat, events and the terminal can't reach the server through
+    /// `discobot-api://`. `DISCOBOT_SERVER_SOCKET=1` does the same.
+    pub unix_socket: bool,
+    /// Keep the server secret in the OS keychain so it stays the same across
+    /// launches, for scripts and tools using the API. Off by default, giving
+    /// each launch a new secret.
+    pub persist_secret: bool,
+    /// Let the frontend fetch the server secret with `get_server_secret`,
+    /// which it still needs for the streaming endpoints `discobot-api://`
+    /// can't proxy. Kept during the migration to the proxy. Turning it off
+    /// leaves those endpoints without a way to authenticate, which disables
+    /// chat, project events, the terminal and web previews.
+    pub legacy_secret_access: bool,
+    /// Ask before quitting while the server has agent tasks in progress.
+    pub confirm_quit_with_active_tasks: bool,
+    /// Always ask before quitting from the tray.
+    pub confirm_quit: bool,
+    /// What closing the main window does: hide it to the tray (the
+    /// default), quit, or ask.
+    pub close_behavior: CloseBehavior,
+    /// Keep the window hidden on launch, leaving the app in the tray.
+    pub start_hidden: bool,
+    /// Hiding the tray icon is refused while the window is hidden, and it
+    /// reappears on a hidden launch or whenever the window is hidden.
+    pub show_tray_icon: bool,
+    /// Stop the server after the window has been hidden this many minutes
+    /// with no agent tasks running; `None`, the default, never suspends it.
+    /// Open SSH sessions and port previews don't count as activity, so
+    /// they're dropped when it stops.
+    pub idle_suspend_minutes: Option<u32>,
+    /// How server output is written to server.log, from the next server
+    /// start on: readable text (the default) or JSON lines.
+    pub log_format: LogFormat,
+    /// Sidecar log level: `debug`, `info`, `warn` or `error`.
+    pub log_level: String,
+    /// Write the server's JSON log records to a text server.log as
+    /// `LEVEL msg key=value` instead of raw JSON.
+    pub render_json_logs: bool,
+    /// Least severe JSON record kept in server.log, e.g. `info` to leave out
+    /// debug records; `None` keeps everything. Doesn't affect the live view.
+    pub log_file_level: Option<String>,
+    /// Level of the app's own shell.log, like `log_level`.
+    /// `DISCOBOT_SHELL_LOG_LEVEL` takes precedence.
+    pub shell_log_level: String,
+    /// Pass the app's whole environment to the server instead of an
+    /// allowlist. `DISCOBOT_INHERIT_ENV=1` does the same.
+    pub inherit_environment: bool,
+    /// Applied on the next server start.
+    pub proxy: ProxySettings,
+    /// Each server run logs to its own `server-<run>.log`. When a run
+    /// starts, the oldest runs beyond `log_retention_runs` (counting the new
+    /// one) or `log_retention_bytes` in total are deleted.
+    /// `DISCOBOT_LOG_RETENTION_RUNS` and `DISCOBOT_LOG_RETENTION_BYTES` take
+    /// precedence.
+    pub log_retention_runs: u32,
+    pub log_retention_bytes: u64,
+    /// Gzip earlier runs' files to `server-<run>.log.gz`. Off keeps them
+    /// plain text for tools like `tail`.
+    pub compress_previous_logs: bool,
+    /// How often buffered server output is written to server.log.
+    pub log_flush_interval_ms: u64,
+    /// Write stdout to `server-<run>.out.log` and stderr to
+    /// `server-<run>.err.log` instead of both to `server-<run>.log`.
+    pub split_log_streams: bool,
+    /// Directory for the server logs instead of the platform's state
+    /// directory, from the next launch on. `DISCOBOT_LOG_DIR` takes
+    /// precedence; either falls back to the default if it isn't writable.
+    pub log_dir: Option<String>,
+    /// BCP 47 language tag (e.g. `de-DE`) to use instead of the OS locale.
+    pub language: Option<String>,
+    pub feature_flags: FeatureFlags,
+    /// Memory and CPUs for the sandbox VM (macOS only); `None` lets the
+    /// server decide.
+    pub vm_memory_mb: Option<u32>,
+    pub vm_cpu_count: Option<u32>,
+    /// Absolute path of a self-built server binary to run instead of the
+    /// bundled one. `DISCOBOT_SERVER_PATH` takes precedence.
+    pub server_path: Option<String>,
+    /// Run the bundled `discobot-server-<profile>` instead of
+    /// `discobot-server`, e.g. `canary` to try a canary backend. Ignored while
+    /// `server_path` is set.
+    pub server_profile: Option<String>,
+    /// Origins allowed to call the server API besides the app's own webview,
+    /// e.g. `http://localhost:5173` for a local dev frontend.
+    pub extra_cors_origins: Vec<String>,
+    /// Accept wildcard entries in `extra_cors_origins`.
+    pub allow_wildcard_cors: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            notifications_enabled: true,
+            restart_on_wake: true,
+            health_check_interval_secs: Some(30),
+            health_check_failures: 3,
+            spawn_attempts: sidecar::DEFAULT_SPAWN_ATTEMPTS,
+            spawn_retry_delay_ms: sidecar::DEFAULT_SPAWN_RETRY_DELAY_MS,
+            vz_kernel_path: None,
+            vz_base_disk_path: None,
+            external_server: None,
+            fixed_port: None,
+            port_range: None,
+            unix_socket: false,
+            persist_secret: false,
+            legacy_secret_access: true,
+            confirm_quit_with_active_tasks: true,
+            confirm_quit: false,
+            close_behavior: CloseBehavior::Hide,
+            start_hidden: false,
+            show_tray_icon: true,
+            idle_suspend_minutes: None,
+            log_format: LogFormat::Text,
+            log_level: crate::DEFAULT_LOG_LEVEL.to_string(),
+            render_json_logs: true,
+            log_file_level: None,
+            shell_log_level: crate::DEFAULT_LOG_LEVEL.to_string(),
+            inherit_environment: false,
+            proxy: ProxySettings::default(),
+            log_retention_runs: logs::DEFAULT_RETENTION_RUNS,
+            log_retention_bytes: logs::DEFAULT_RETENTION_BYTES,
+            compress_previous_logs: true,
+            log_flush_interval_ms: logs::DEFAULT_FLUSH_INTERVAL_MS,
+            split_log_streams: false,
+            log_dir: None,
+            language: None,
+            feature_flags: FeatureFlags::default(),
+            vm_memory_mb: None,
+            vm_cpu_count: None,
+            server_path: None,
+            server_profile: None,
+            extra_cors_origins: Vec::new(),
+            allow_wildcard_cors: false,
+        }
+    }
+}
+
+/// Ports below this need elevated privileges on most systems.
+const MIN_FIXED_PORT: u16 = 1024;
+
+pub fn validate_fixed_port(port: u16) -> Result<(), String> {
+    if port < MIN_FIXED_PORT {
+        return Err(format!(
+            "Server port must be {} or above, got {}",
+            MIN_FIXED_PORT, port
+        ));
+    }
+    Ok(())
+}
+
+impl Settings {
+    /// Whether going from `self` to `other` changes anything the sidecar only
+    /// reads when it starts.
+    pub fn server_differs(&self, other: &Settings) -> bool {
+        self.vz_kernel_path != other.vz_kernel_path
+            || self.vz_base_disk_path != other.vz_base_disk_path
+            || self.log_level != other.log_level
+            || self.inherit_environment != other.inherit_environment
+            || self.proxy != other.proxy
+            || self.language != other.language
+            || self.feature_flags != other.feature_flags
+            || self.vm_memory_mb != other.vm_memory_mb
+            || self.vm_cpu_count != other.vm_cpu_count
+            || self.server_path != other.server_path
+            || self.server_profile != other.server_profile
+            || self.extra_cors_origins != other.extra_cors_origins
+            || self.allow_wildcard_cors != other.allow_wildcard_cors
+    }
+}
+
+fn get_settings_path() -> Result<PathBuf, String> {
+    let config_dir =
+        dirs::config_dir().ok_or_else(|| "Could not determine config directory".to_string())?;
+    Ok(config_dir.join("discobot").join("settings.json"))
+}
+
+/// Load settings from disk, falling back to defaults if the file is missing or invalid.
+pub fn load() -> Settings {
+    let path = match get_settings_path() {
+        Ok(path) => path,
+        Err(e) => {
+            warn!("{}, using default settings", e);
+            return Settings::default();
+        }
+    };
+
+    match fs::read_to_string(&path) {
+        Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+            warn!("Invalid settings file {}: {}", path.display(), e);
+            Settings::default()
+        }),
+        Err(_) => Settings::default(),
+    }
+}
+
+pub fn save(settings: &Settings) -> Result<(), String> {
+    let path = get_settings_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+    let contents = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    fs::write(&path, contents).map_err(|e| format!("Failed to write settings: {}", e))
+}
+
+#[tauri::command]
+pub fn get_settings(state: tauri::State<'_, Mutex<Settings>>) -> Settings {
+    state.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn update_settings(app: tauri::AppHandle, mut settings: Settings) -> Result<(), String> {
+    if let Some(port) = settings.fixed_port {
+        validate_fixed_port(port)?;
+    }
+    ports::validate(settings.port_range)?;
+    cors::validate(&settings)?;
+    settings.log_level = crate::validate_log_level(&settings.log_level)?;
+    if let Some(level) = &settings.log_file_level {
+        settings.log_file_level = Some(crate::validate_log_level(level)?);
+    }
+    settings.shell_log_level = crate::validate_log_level(&settings.shell_log_level)?;
+    logs::validate_retention(settings.log_retention_runs, settings.log_retention_bytes)?;
+    logs::validate_log_dir(settings.log_dir.as_deref())?;
+    sidecar::profile_binary(settings.server_profile.as_deref())?;
+    save(&settings)?;
+    let (restart, persist_secret) = {
+        let state = app.state::<Mutex<Settings>>();
+        let mut current = state.lock().unwrap();
+        let restart = current.server_differs(&settings);
+        let persist_secret =
+            (current.persist_secret != settings.persist_secret).then_some(settings.persist_secret);
+        logs::set_split_streams(settings.split_log_streams);
+        structured_logs::configure(&settings);
+        shell_log::configure(&settings);
+        *current = settings;
+        (restart, persist_secret)
+    };
+
+    if let Some(persist) = persist_secret {
+        secret_store::apply_setting(&app, persist);
+    }
+    if restart {
+        crate::mark_restart_required(&app);
+    }
+    Ok(())
+}
+
+/// Set or clear the fixed server port, used from the next launch on.
+#[tauri::command]
+pub fn set_fixed_port(
+    state: tauri::State<'_, Mutex<Settings>>,
+    port: Option<u16>,
+) -> Result<(), String> {
+    if let Some(port) = port {
+        validate_fixed_port(port)?;
+    }
+    let mut settings = state.lock().unwrap();
+    let mut updated = settings.clone();
+    updated.fixed_port = port;
+    save(&updated)?;
+    *settings = updated;
+    Ok(())
+}
@@ -0,0 +1,226 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+use log::{debug, warn, LevelFilter, Log, Metadata, Record};
+use tauri::ipc::Invoke;
+use tauri::Runtime;
+
+use crate::settings::Settings;
+
+/// The shell's own log, kept in the log directory next to the server's runs.
+const SHELL_LOG: &str = "shell.log";
+/// The previous shell.log, once it outgrew `MAX_SHELL_LOG_BYTES`.
+const ROTATED_SHELL_LOG: &str = "shell.log.1";
+const MAX_SHELL_LOG_BYTES: u64 = 1024 * 1024;
+/// Lines kept for shell.log until the log directory is chosen.
+const MAX_PENDING_LINES: usize = 200;
+/// Takes precedence over `Settings::shell_log_level`.
+const LEVEL_ENV: &str = "DISCOBOT_SHELL_LOG_LEVEL";
+/// Records from other crates, like Tauri's plugins, are only kept from
+/// `Warn` up.
+const CRATE_TARGET: &str = env!("CARGO_CRATE_NAME");
+
+static LOGGER: ShellLogger = ShellLogger;
+/// `Settings::shell_log_level` as a `LevelFilter`.
+static LEVEL: AtomicUsize = AtomicUsize::new(LevelFilter::Info as usize);
+static SINK: Mutex<Sink> = Mutex::new(Sink::Pending(Vec::new()));
+
+enum Sink {
+    /// The log directory isn't chosen yet.
+    Pending(Vec<String>),
+    Open(ShellFile),
+    /// There's no usable log directory, so only stderr gets the lines.
+    Closed,
+}
+
+struct ShellFile {
+    file: File,
+    path: PathBuf,
+    len: u64,
+}
+
+impl ShellFile {
+    fn open(path: PathBuf) -> Result<Self, String> {
+        let file = open_append(&path)?;
+        let len = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+        let mut shell_file = Self { file, path, len };
+        if len >= MAX_SHELL_LOG_BYTES {
+            shell_file.rotate();
+        }
+        Ok(shell_file)
+    }
+
+    fn write_line(&mut self, line: &str) {
+        if writeln!(self.file, "{}", line).is_ok() {
+            self.len += line.len() as u64 + 1;
+        }
+        if self.len >= MAX_SHELL_LOG_BYTES {
+            self.rotate();
+        }
+    }
+
+    /// Move the file to shell.log.1, replacing the one there, and continue
+    /// in a new shell.log. If that fails it's tried again after another
+    /// `MAX_SHELL_LOG_BYTES`.
+    fn rotate(&mut self) {
+        self.len = 0;
+        if fs::rename(&self.path, self.path.with_file_name(ROTATED_SHELL_LOG)).is_ok() {
+            if let Ok(file) = open_append(&self.path) {
+                self.file = file;
+            }
+        }
+    }
+}
+
+fn open_append(path: &Path) -> Result<File, String> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))
+}
+
+struct ShellLogger;
+
+impl Log for ShellLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let max = if metadata.target().starts_with(CRATE_TARGET) {
+            level()
+        } else {
+            LevelFilter::Warn
+        };
+        metadata.level() <= max
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let target = record
+            .target()
+            .strip_prefix(CRATE_TARGET)
+            .and_then(|module| module.strip_prefix("::"))
+            .unwrap_or(record.target());
+        let line = format!(
+            "{} {:<5} [{}] {}",
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+            record.level(),
+            target,
+            record.args()
+        );
+        eprintln!("{}", line);
+        match &mut *SINK.lock().unwrap_or_else(PoisonError::into_inner) {
+            Sink::Pending(lines) => {
+                if lines.len() == MAX_PENDING_LINES {
+                    lines.remove(0);
+                }
+                lines.push(line);
+            }
+            Sink::Open(file) => file.write_line(&line),
+            Sink::Closed => {}
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+fn level() -> LevelFilter {
+    let level = LEVEL.load(Ordering::SeqCst);
+    LevelFilter::iter()
+        .find(|filter| *filter as usize == level)
+        .unwrap_or(LevelFilter::Info)
+}
+
+/// Send the `log` macros to stderr and shell.log. Called first thing at
+/// startup, before settings are loaded; lines logged before `open` are
+/// written once it's called.
+pub fn init() {
+    let _ = log::set_logger(&LOGGER);
+    // A bad DISCOBOT_SHELL_LOG_LEVEL is reported once settings are loaded
+    let _ = set_level(crate::DEFAULT_LOG_LEVEL);
+}
+
+/// Apply `Settings::shell_log_level`; called at startup and whenever
+/// settings change.
+pub fn configure(settings: &Settings) {
+    if let Err(e) = set_level(&settings.shell_log_level) {
+        warn!("Ignoring {}: {}", LEVEL_ENV, e);
+    }
+}
+
+/// Log at `setting`, unless `DISCOBOT_SHELL_LOG_LEVEL` is set to a valid
+/// level.
+fn set_level(setting: &str) -> Result<(), String> {
+    let from_env = std::env::var(LEVEL_ENV)
+        .ok()
+        .map(|level| crate::validate_log_level(&level))
+        .transpose();
+    let level = match &from_env {
+        Ok(Some(level)) => level,
+        _ => setting,
+    };
+    let level = LevelFilter::from_str(level).unwrap_or(LevelFilter::Info);
+    LEVEL.store(level as usize, Ordering::SeqCst);
+    log::set_max_level(level.max(LevelFilter::Warn));
+    from_env.map(|_| ())
+}
+
+/// Wrap the app's command handler to log each invocation at `debug`.
+pub fn log_commands<R: Runtime>(
+    handler: impl Fn(Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        debug!(
+            "Command {} from window \"{}\"",
+            invoke.message.command(),
+            invoke.message.webview_ref().label()
+        );
+        handler(invoke)
+    }
+}
+
+/// Start writing shell.log in this launch's log directory, or only to
+/// stderr if there's none.
+pub fn open(dir: Option<&Path>) {
+    let opened = dir.map(|dir| ShellFile::open(path(dir)));
+    let error = {
+        let mut sink = SINK.lock().unwrap_or_else(PoisonError::into_inner);
+        let Sink::Pending(pending) = &mut *sink else {
+            return;
+        };
+        let pending = std::mem::take(pending);
+        *sink = Sink::Closed;
+        match opened {
+            Some(Ok(mut file)) => {
+                for line in pending {
+                    file.write_line(&line);
+                }
+                *sink = Sink::Open(file);
+                None
+            }
+            Some(Err(e)) => Some(e),
+            None => None,
+        }
+    };
+    if let Some(e) = error {
+        warn!("{}", e);
+    }
+}
+
+/// shell.log in `dir`.
+pub fn path(dir: &Path) -> PathBuf {
+    dir.join(SHELL_LOG)
+}
+
+/// shell.log and the rotated shell.log.1 in `dir`, if they exist.
+pub fn files(dir: &Path) -> Vec<PathBuf> {
+    [SHELL_LOG, ROTATED_SHELL_LOG]
+        .iter()
+        .map(|name| dir.join(name))
+        .filter(|path| path.exists())
+        .collect()
+}
@@ -0,0 +1,254 @@
+use std::path::{Path, PathBuf};
+
+use log::{error, warn};
+use serde::Serialize;
+use tauri::Emitter;
+use tauri_plugin_clipboard_manager::ClipboardExt;
+use tauri_plugin_dialog::{
+    DialogExt, MessageDialogButtons, MessageDialogKind, MessageDialogResult,
+};
+use tauri_plugin_opener::OpenerExt;
+use tauri_plugin_shell::process::Command;
+use tauri_plugin_shell::ShellExt;
+
+use crate::logs;
+
+/// Name of the bundled server binary (see `externalBin` in tauri.conf.json).
+pub const NAME: &str = "discobot-server";
+
+/// Target triple the app was built for; the sidecar is bundled per triple.
+const TARGET_TRIPLE: &str = env!("DISCOBOT_TARGET_TRIPLE");
+
+/// Event emitted when the bundled server binary isn't there to launch.
+const BINARY_MISSING_EVENT: &str = "server-binary-missing";
+
+#[derive(Clone, Serialize)]
+struct BinaryMissing {
+    name: &'static str,
+    target: &'static str,
+}
+
+const OPEN_LOGS_BUTTON: &str = "Open Log Folder";
+const COPY_DIAGNOSTICS_BUTTON: &str = "Copy Diagnostics";
+const CLOSE_BUTTON: &str = "Close";
+
+/// Defaults for `Settings::spawn_attempts` and
+/// `Settings::spawn_retry_delay_ms`.
+pub const DEFAULT_SPAWN_ATTEMPTS: u32 = 3;
+pub const DEFAULT_SPAWN_RETRY_DELAY_MS: u64 = 500;
+/// Most spawn attempts made, whatever the setting says.
+pub const MAX_SPAWN_ATTEMPTS: u32 = 10;
+/// Longest wait between spawn attempts, whatever the setting says.
+pub const MAX_SPAWN_RETRY_DELAY_MS: u64 = 30_000;
+
+/// Absolute path of a self-built server to run instead of the bundled one.
+/// Takes precedence over `Settings::server_path`.
+const PATH_ENV: &str = "DISCOBOT_SERVER_PATH";
+
+/// The server binary to run instead of the bundled sidecar, if one is
+/// configured. Relative paths are refused since the app's working directory
+/// depends on how it was launched.
+pub fn path_override(setting: Option<&str>) -> Result<Option<PathBuf>, String> {
+    let path = match std::env::var(PATH_ENV) {
+        Ok(value) if !value.is_empty() => value,
+        _ => match setting {
+            Some(value) if !value.is_empty() => value.to_string(),
+            _ => return Ok(None),
+        },
+    };
+    let path = PathBuf::from(path);
+    if !path.is_absolute() {
+        return Err(format!(
+            "Server path override '{}' must be an absolute path",
+            path.display()
+        ));
+    }
+    Ok(Some(path))
+}
+
+/// Sidecar binary for `Settings::server_profile`: `discobot-server` when
+/// unset, otherwise `discobot-server-<profile>`, e.g. `discobot-server-canary`.
+pub fn profile_binary(profile: Option<&str>) -> Result<String, String> {
+    let Some(profile) = profile.filter(|profile| !profile.is_empty()) else {
+        return Ok(NAME.to_string());
+    };
+    if !profile
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "Invalid server profile '{}', expected letters, digits, '-' or '_'",
+            profile
+        ));
+    }
+    Ok(format!("{}-{}", NAME, profile))
+}
+
+/// Make sure a profile's sidecar was bundled. Unlike the default one, a
+/// missing profile binary is a configuration mistake, not a broken install.
+pub fn check_profile(name: &str, path: &Path) -> Result<(), String> {
+    check(path).map_err(|e| {
+        format!(
+            "Server profile binary {} isn't usable for {}: {} ({})",
+            name,
+            TARGET_TRIPLE,
+            e,
+            path.display()
+        )
+    })
+}
+
+/// Where the shell plugin will look for the sidecar `name`: next to the app
+/// executable.
+pub fn path(name: &str) -> Result<PathBuf, String> {
+    let exe = std::env::current_exe()
+        .map_err(|e| format!("Failed to locate the app executable: {}", e))?;
+    let dir = exe
+        .parent()
+        .ok_or_else(|| "App executable has no parent directory".to_string())?;
+    Ok(dir.join(format!("{}{}", name, std::env::consts::EXE_SUFFIX)))
+}
+
+/// A fresh copy of the command `template` was made from, since spawning
+/// consumes a command and a failed spawn is retried. `clear_env` repeats its
+/// `env_clear`, which `template` doesn't record.
+pub fn copy_command(
+    app: &tauri::AppHandle,
+    template: &std::process::Command,
+    clear_env: bool,
+) -> Command {
+    let mut command = app
+        .shell()
+        .command(template.get_program())
+        .args(template.get_args());
+    if clear_env {
+        command = command.env_clear();
+    }
+    command = command.envs(
+        template
+            .get_envs()
+            .filter_map(|(key, value)| Some((key, value?))),
+    );
+    if let Some(dir) = template.get_current_dir() {
+        command = command.current_dir(dir);
+    }
+    command
+}
+
+/// Make sure the sidecar exists and can be executed, so a broken install is
+/// reported as such rather than as a generic spawn failure.
+pub fn check(path: &Path) -> Result<(), String> {
+    let metadata = std::fs::metadata(path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => "Server binary is missing".to_string(),
+        _ => format!("Failed to inspect server binary: {}", e),
+    })?;
+    if !metadata.is_file() {
+        return Err("Server binary is not a regular file".to_string());
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if metadata.permissions().mode() & 0o111 == 0 {
+            return Err("Server binary is not executable".to_string());
+        }
+    }
+
+    Ok(())
+}
+
+/// Let the next spawned process inherit `listener` and return its descriptor
+/// number, to be passed as `LISTEN_FD` or `SSH_LISTEN_FD`. Rust opens sockets
+/// close-on-exec, so that flag is cleared here.
+#[cfg(unix)]
+pub fn inheritable_fd(listener: &std::net::TcpListener) -> Result<i32, String> {
+    use std::os::fd::AsRawFd;
+
+    let fd = listener.as_raw_fd();
+    // SAFETY: `fd` is an open descriptor owned by `listener`.
+    let cleared = unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFD);
+        flags != -1 && libc::fcntl(fd, libc::F_SETFD, flags & !libc::FD_CLOEXEC) != -1
+    };
+    if !cleared {
+        return Err(format!(
+            "Failed to make a listening socket inheritable: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    Ok(fd)
+}
+
+/// Tell the frontend the bundled server wasn't found, naming the binary and
+/// target triple it was expected for.
+pub fn report_missing(app: &tauri::AppHandle) {
+    error!("No {} binary bundled for {}", NAME, TARGET_TRIPLE);
+    let _ = app.emit(
+        BINARY_MISSING_EVENT,
+        BinaryMissing {
+            name: NAME,
+            target: TARGET_TRIPLE,
+        },
+    );
+}
+
+/// Tell the user the bundled server can't be started and offer ways to
+/// gather information for a bug report. Doesn't block the caller.
+pub fn show_failure_dialog(app: &tauri::AppHandle, path: &Path, error: &str) {
+    let message = format!(
+        "Discobot couldn't start its bundled server.\n\n\
+         {}\n{}\n\n\
+         The app may be damaged, or antivirus software may have quarantined \
+         the server. Reinstalling Discobot usually fixes this.",
+        error,
+        path.display()
+    );
+    let diagnostics = diagnostics(app, path, error);
+
+    let handle = app.clone();
+    app.dialog()
+        .message(message)
+        .title("Discobot server unavailable")
+        .kind(MessageDialogKind::Error)
+        .buttons(MessageDialogButtons::YesNoCancelCustom(
+            OPEN_LOGS_BUTTON.to_string(),
+            COPY_DIAGNOSTICS_BUTTON.to_string(),
+            CLOSE_BUTTON.to_string(),
+        ))
+        .show_with_result(move |result| match result {
+            MessageDialogResult::Custom(button) if button == OPEN_LOGS_BUTTON => {
+                if let Ok(log_path) = logs::main_log_path() {
+                    if let Err(e) = handle.opener().reveal_item_in_dir(log_path) {
+                        warn!("Failed to open log folder: {}", e);
+                    }
+                }
+            }
+            MessageDialogResult::Custom(button) if button == COPY_DIAGNOSTICS_BUTTON => {
+                if let Err(e) = handle.clipboard().write_text(diagnostics) {
+                    warn!("Failed to copy diagnostics: {}", e);
+                }
+            }
+            _ => {}
+        });
+}
+
+fn diagnostics(app: &tauri::AppHandle, path: &Path, error: &str) -> String {
+    let log_files: Vec<String> = logs::log_files()
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect();
+    format!(
+        "Discobot {}\nOS: {} {}\nTarget: {}\nServer binary: {}\nError: {}\nLog files: {}",
+        app.package_info().version,
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        TARGET_TRIPLE,
+        path.display(),
+        error,
+        if log_files.is_empty() {
+            logs::log_dir().err().unwrap_or_default()
+        } else {
+            log_files.join(", ")
+        }
+    )
+}
@@ -0,0 +1,129 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use log::{info, warn};
+
+use crate::shutdown_server;
+
+/// Set by the first signal; a second one exits without waiting.
+static STOPPING: AtomicBool = AtomicBool::new(false);
+
+/// Stop the sidecar and exit when the app is interrupted from a terminal
+/// (Ctrl-C), terminated, or its console window is closed, which otherwise
+/// skips the quit path and leaves the server holding its ports.
+pub fn spawn_signal_handler(app: tauri::AppHandle) {
+    let handle = app.clone();
+    listen("interrupt", async move {
+        loop {
+            tokio::signal::ctrl_c().await?;
+            on_signal(&handle, "interrupt");
+        }
+    });
+
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let handle = app.clone();
+        listen("SIGTERM", async move {
+            let mut deliveries = signal(SignalKind::terminate())?;
+            while deliveries.recv().await.is_some() {
+                on_signal(&handle, "SIGTERM");
+            }
+            Ok(())
+        });
+    }
+
+    #[cfg(windows)]
+    {
+        use tokio::signal::windows;
+        let handle = app.clone();
+        listen("Ctrl-Break", async move {
+            let mut deliveries = windows::ctrl_break()?;
+            while deliveries.recv().await.is_some() {
+                on_signal(&handle, "Ctrl-Break");
+            }
+            Ok(())
+        });
+        let handle = app.clone();
+        listen("console close", async move {
+            let mut deliveries = windows::ctrl_close()?;
+            while deliveries.recv().await.is_some() {
+                on_signal(&handle, "console close");
+            }
+            Ok(())
+        });
+        let handle = app.clone();
+        listen("shutdown", async move {
+            let mut deliveries = windows::ctrl_shutdown()?;
+            while deliveries.recv().await.is_some() {
+                on_signal(&handle, "shutdown");
+            }
+            Ok(())
+        });
+    }
+}
+
+/// Run a loop over a signal's deliveries in the background, logging if the
+/// signal can't be listened for.
+fn listen(
+    name: &'static str,
+    deliveries: impl Future<Output = std::io::Result<()>> + Send + 'static,
+) {
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = deliveries.await {
+            warn!("Failed to listen for {}: {}", name, e);
+        }
+    });
+}
+
+#[derive(Debug, PartialEq)]
+enum Delivery {
+    /// Stop the server, then exit.
+    First,
+    /// The server is already being stopped; exit without waiting.
+    Again,
+}
+
+fn delivery(stopping: &AtomicBool) -> Delivery {
+    if stopping.swap(true, Ordering::SeqCst) {
+        Delivery::Again
+    } else {
+        Delivery::First
+    }
+}
+
+/// Handle one delivery of a signal. The shutdown runs in its own task so the
+/// listener keeps receiving and a second delivery isn't left waiting on it.
+fn on_signal(app: &tauri::AppHandle, name: &'static str) {
+    if delivery(&STOPPING) == Delivery::Again {
+        warn!("Received {} again, exiting immediately", name);
+        std::process::exit(1);
+    }
+    info!("Received {}, stopping server", name);
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let handle = app.clone();
+        let _ = tauri::async_runtime::spawn_blocking(move || shutdown_server(&handle)).await;
+        app.exit(0);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_delivery_stops_the_server() {
+        let stopping = AtomicBool::new(false);
+        assert_eq!(delivery(&stopping), Delivery::First);
+        assert!(stopping.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn later_deliveries_exit_immediately() {
+        let stopping = AtomicBool::new(false);
+        delivery(&stopping);
+        assert_eq!(delivery(&stopping), Delivery::Again);
+        assert_eq!(delivery(&stopping), Delivery::Again);
+    }
+}
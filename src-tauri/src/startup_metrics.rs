@@ -0,0 +1,111 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::Manager;
+
+use crate::{health, ServerState};
+
+/// Health probes after the ready line before giving up on timing it.
+const HEALTH_PROBE_ATTEMPTS: u32 = 20;
+const HEALTH_PROBE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// When the current sidecar reached each startup milestone.
+#[derive(Clone, Copy, Default)]
+pub struct StartupTimes {
+    /// The app began preparing logs and spawning the sidecar.
+    requested: Option<Instant>,
+    spawned: Option<Instant>,
+    first_output: Option<Instant>,
+    /// The sidecar reported its port or socket.
+    ready: Option<Instant>,
+    /// The first `/health` probe after `ready` passed.
+    healthy: Option<Instant>,
+}
+
+/// Which milestone to record.
+#[derive(Clone, Copy)]
+pub enum Milestone {
+    Spawned,
+    FirstOutput,
+    Ready,
+    Healthy,
+}
+
+impl StartupTimes {
+    /// Start timing a new sidecar from now.
+    pub fn begin() -> Self {
+        Self {
+            requested: Some(Instant::now()),
+            ..Self::default()
+        }
+    }
+
+    /// Record a milestone unless it was already reached.
+    pub fn mark(&mut self, milestone: Milestone) {
+        let time = match milestone {
+            Milestone::Spawned => &mut self.spawned,
+            Milestone::FirstOutput => &mut self.first_output,
+            Milestone::Ready => &mut self.ready,
+            Milestone::Healthy => &mut self.healthy,
+        };
+        time.get_or_insert_with(Instant::now);
+    }
+}
+
+/// Milliseconds from the start request to each milestone, `None` for those
+/// not reached yet.
+#[derive(Serialize)]
+pub struct StartupMetrics {
+    spawned_ms: Option<u64>,
+    first_output_ms: Option<u64>,
+    ready_ms: Option<u64>,
+    healthy_ms: Option<u64>,
+}
+
+impl From<StartupTimes> for StartupMetrics {
+    fn from(times: StartupTimes) -> Self {
+        let since_requested = |time: Option<Instant>| {
+            let elapsed = time?.checked_duration_since(times.requested?)?;
+            Some(elapsed.as_millis() as u64)
+        };
+        Self {
+            spawned_ms: since_requested(times.spawned),
+            first_output_ms: since_requested(times.first_output),
+            ready_ms: since_requested(times.ready),
+            healthy_ms: since_requested(times.healthy),
+        }
+    }
+}
+
+/// Record a milestone for the sidecar of `generation`, ignoring one that has
+/// already been replaced.
+pub fn mark(app: &tauri::AppHandle, generation: u64, milestone: Milestone) {
+    let state = app.state::<Mutex<ServerState>>();
+    let mut state = state.lock().unwrap();
+    if state.generation == generation {
+        state.startup_times.mark(milestone);
+    }
+}
+
+/// Time how long after its ready line the sidecar first passes a health
+/// check.
+pub fn probe_health(app: &tauri::AppHandle, generation: u64) {
+    let endpoint = app.state::<Mutex<ServerState>>().lock().unwrap().endpoint();
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        for _ in 0..HEALTH_PROBE_ATTEMPTS {
+            if health::check_health(&endpoint).await {
+                mark(&app, generation, Milestone::Healthy);
+                return;
+            }
+            tokio::time::sleep(HEALTH_PROBE_INTERVAL).await;
+        }
+    });
+}
+
+/// How long the current sidecar took to reach each startup milestone.
+#[tauri::command]
+pub fn get_startup_metrics(state: tauri::State<'_, Mutex<ServerState>>) -> StartupMetrics {
+    state.lock().unwrap().startup_times.into()
+}
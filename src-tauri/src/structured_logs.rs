@@ -0,0 +1,193 @@
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::Mutex;
+
+use serde_json::{Map, Value};
+
+use crate::settings::{self, Settings};
+
+/// `Settings::render_json_logs`, kept here so the log writer can read it
+/// without access to the app's state.
+static RENDER: AtomicBool = AtomicBool::new(true);
+/// `Settings::log_file_level` as a `Level`, or `NO_MINIMUM`.
+static MIN_LEVEL: AtomicU8 = AtomicU8::new(NO_MINIMUM);
+const NO_MINIMUM: u8 = 0;
+
+/// Severity of a JSON log line, as written by the server's slog handler.
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+enum Level {
+    Debug = 1,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    /// Parse `debug`, `INFO`, or slog's offset levels like `WARN+2`.
+    fn parse(level: &str) -> Option<Self> {
+        let name = level.split(['+', '-']).next().unwrap_or(level);
+        match name.to_ascii_lowercase().as_str() {
+            "debug" | "trace" => Some(Self::Debug),
+            "info" => Some(Self::Info),
+            "warn" | "warning" => Some(Self::Warn),
+            "error" | "fatal" => Some(Self::Error),
+            _ => None,
+        }
+    }
+
+    fn from_u8(value: u8) -> Option<Self> {
+        [Self::Debug, Self::Info, Self::Warn, Self::Error]
+            .into_iter()
+            .find(|level| *level as u8 == value)
+    }
+}
+
+/// Apply `Settings::render_json_logs` and `Settings::log_file_level`; called
+/// at startup and whenever settings change.
+pub fn configure(settings: &Settings) {
+    RENDER.store(settings.render_json_logs, Ordering::SeqCst);
+    let min_level = settings.log_file_level.as_deref().and_then(Level::parse);
+    MIN_LEVEL.store(
+        min_level.map_or(NO_MINIMUM, |level| level as u8),
+        Ordering::SeqCst,
+    );
+}
+
+/// A line of server output that's a JSON log record.
+pub struct Record {
+    level: Option<Level>,
+    level_name: Option<String>,
+    time: Option<String>,
+    msg: String,
+    attrs: Map<String, Value>,
+}
+
+impl Record {
+    /// The record on this line, if it's a JSON object with a `msg`.
+    pub fn parse(line: &str) -> Option<Self> {
+        if !line.trim_start().starts_with('{') {
+            return None;
+        }
+        let mut attrs: Map<String, Value> = serde_json::from_str(line).ok()?;
+        let msg = match attrs.remove("msg")? {
+            Value::String(msg) => msg,
+            other => other.to_string(),
+        };
+        let level_name = match attrs.remove("level") {
+            Some(Value::String(level)) => Some(level),
+            _ => None,
+        };
+        let time = match attrs.remove("time") {
+            Some(Value::String(time)) => Some(time),
+            _ => None,
+        };
+        Some(Self {
+            level: level_name.as_deref().and_then(Level::parse),
+            level_name,
+            time,
+            msg,
+            attrs,
+        })
+    }
+
+    /// Whether the record is below `Settings::log_file_level` and should stay
+    /// out of server.log. Records without a level are kept.
+    pub fn filtered(&self) -> bool {
+        let min_level = Level::from_u8(MIN_LEVEL.load(Ordering::SeqCst));
+        matches!((self.level, min_level), (Some(level), Some(min)) if level < min)
+    }
+
+    /// The record's own time, if it has one.
+    pub fn time(&self) -> Option<&str> {
+        self.time.as_deref()
+    }
+
+    /// `LEVEL msg key=value ...` for the text log.
+    pub fn render(&self) -> String {
+        let mut rendered = match &self.level_name {
+            Some(level) => format!("{} {}", level.to_uppercase(), self.msg),
+            None => self.msg.clone(),
+        };
+        for (key, value) in &self.attrs {
+            let value = match value {
+                Value::String(value) if !value.contains(char::is_whitespace) => value.clone(),
+                other => other.to_string(),
+            };
+            rendered.push_str(&format!(" {}={}", key, value));
+        }
+        rendered
+    }
+}
+
+/// Whether JSON records are rendered readably in a text server.log.
+pub fn render_enabled() -> bool {
+    RENDER.load(Ordering::SeqCst)
+}
+
+/// Set the least severe level of JSON records kept in server.log, or `None`
+/// to keep all. The live log view still receives every line.
+#[tauri::command]
+pub fn set_log_file_level(
+    state: tauri::State<'_, Mutex<Settings>>,
+    level: Option<String>,
+) -> Result<(), String> {
+    let level = level
+        .filter(|level| !level.is_empty())
+        .map(|level| crate::validate_log_level(&level))
+        .transpose()?;
+    let mut current = state.lock().unwrap();
+    let mut updated = current.clone();
+    updated.log_file_level = level;
+    settings::save(&updated)?;
+    configure(&updated);
+    *current = updated;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_log_file_level(state: tauri::State<'_, Mutex<Settings>>) -> Option<String> {
+    state.lock().unwrap().log_file_level.clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_levels() {
+        assert!(Level::parse("debug") == Some(Level::Debug));
+        assert!(Level::parse("INFO") == Some(Level::Info));
+        assert!(Level::parse("WARN+2") == Some(Level::Warn));
+        assert!(Level::parse("ERROR-4") == Some(Level::Error));
+        assert!(Level::parse("verbose").is_none());
+    }
+
+    #[test]
+    fn only_json_records_with_a_msg_parse() {
+        assert!(Record::parse("plain text").is_none());
+        assert!(Record::parse("{not json").is_none());
+        assert!(Record::parse(r#"{"level":"INFO"}"#).is_none());
+        assert!(Record::parse(r#"  {"msg":"hi"}"#).is_some());
+    }
+
+    #[test]
+    fn renders_level_msg_and_attrs() {
+        let record = Record::parse(
+            r#"{"time":"2024-01-02T03:04:05Z","level":"warn","msg":"slow","path":"/api","took ms":12,"note":"two words"}"#,
+        )
+        .unwrap();
+        assert_eq!(record.time(), Some("2024-01-02T03:04:05Z"));
+        assert!(record.level == Some(Level::Warn));
+        assert_eq!(
+            record.render(),
+            r#"WARN slow note="two words" path=/api took ms=12"#
+        );
+    }
+
+    #[test]
+    fn renders_records_without_a_level() {
+        let record = Record::parse(r#"{"msg":42}"#).unwrap();
+        assert_eq!(record.time(), None);
+        assert!(!record.filtered());
+        assert_eq!(record.render(), "42");
+    }
+}
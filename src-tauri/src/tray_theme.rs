@@ -0,0 +1,43 @@
+use log::warn;
+use tauri::image::Image;
+use tauri::{Manager, Theme};
+
+use crate::TRAY_ID;
+
+/// Monochrome tray icon, drawn black for light backgrounds.
+const TRAY_ICON: &[u8] = include_bytes!("../icons/tray-icon@2x.png");
+
+/// The tray icon for `theme`. macOS recolors the template image itself;
+/// elsewhere a dark theme gets a white copy, since the black one disappears
+/// on a dark taskbar.
+pub fn icon(theme: Theme) -> tauri::Result<Image<'static>> {
+    let icon = Image::from_bytes(TRAY_ICON)?;
+    if cfg!(target_os = "macos") || theme != Theme::Dark {
+        return Ok(icon);
+    }
+    let mut rgba = icon.rgba().to_vec();
+    for pixel in rgba.chunks_exact_mut(4) {
+        pixel[..3].fill(u8::MAX);
+    }
+    Ok(Image::new_owned(rgba, icon.width(), icon.height()))
+}
+
+/// The system theme as the main window sees it, light if unknown.
+pub fn current(app: &tauri::AppHandle) -> Theme {
+    app.get_webview_window("main")
+        .and_then(|window| window.theme().ok())
+        .unwrap_or(Theme::Light)
+}
+
+/// Swap the tray icon to match a new system theme, except on macOS.
+pub fn apply(app: &tauri::AppHandle, theme: Theme) {
+    if cfg!(target_os = "macos") {
+        return;
+    }
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    if let Err(e) = icon(theme).and_then(|icon| tray.set_icon(Some(icon))) {
+        warn!("Failed to update the tray icon for the theme: {}", e);
+    }
+}
@@ -0,0 +1,350 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use keyring::Entry;
+use zeroize::Zeroizing;
+
+/// Longest key name accepted, well within every keychain's limits.
+const MAX_KEY_LENGTH: usize = 128;
+
+/// Key names, kept in the config directory since keychains can't list the
+/// items of a service. Names only; values never touch this file.
+const INDEX_FILE: &str = "secret-keys.json";
+
+/// Keychain for secrets the frontend stores, e.g. provider API keys, so they
+/// stay out of the webview's localStorage. The service is derived from the
+/// app identifier and the frontend only ever names a key within it, so it
+/// can't reach other keychain items.
+fn service(app: &tauri::AppHandle) -> String {
+    format!("{}.user-secrets", app.config().identifier)
+}
+
+fn validate_key(key: &str) -> Result<(), String> {
+    if key.is_empty() || key.len() > MAX_KEY_LENGTH {
+        return Err(format!(
+            "Secret key names must be 1 to {} characters",
+            MAX_KEY_LENGTH
+        ));
+    }
+    if !key
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        return Err(format!(
+            "Invalid secret key name '{}', expected letters, digits, '-', '_' or '.'",
+            key
+        ));
+    }
+    Ok(())
+}
+
+/// Describe a keychain failure without the value involved.
+fn keychain_error(action: &str, e: keyring::Error) -> String {
+    match e {
+        keyring::Error::NoStorageAccess(_) => format!(
+            "Failed to {}: the keychain is locked or access was denied",
+            action
+        ),
+        keyring::Error::TooLong(..) => format!("Failed to {}: the value is too long", action),
+        e => format!("Failed to {}: {}", action, e),
+    }
+}
+
+fn get_index_path() -> Result<PathBuf, String> {
+    let config_dir =
+        dirs::config_dir().ok_or_else(|| "Could not determine config directory".to_string())?;
+    Ok(config_dir.join("discobot").join(INDEX_FILE))
+}
+
+fn read_index() -> BTreeSet<String> {
+    get_index_path()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn update_index(change: impl FnOnce(&mut BTreeSet<String>)) -> Result<(), String> {
+    static INDEX_LOCK: Mutex<()> = Mutex::new(());
+    let _guard = INDEX_LOCK.lock().unwrap();
+    let path = get_index_path()?;
+    let mut keys = read_index();
+    change(&mut keys);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+    let contents = serde_json::to_string_pretty(&keys)
+        .map_err(|e| format!("Failed to serialize secret keys: {}", e))?;
+    fs::write(&path, contents).map_err(|e| format!("Failed to write secret keys: {}", e))
+}
+
+fn entry(service: &str, key: &str) -> Result<Entry, String> {
+    Entry::new(service, key).map_err(|e| keychain_error("open the keychain", e))
+}
+
+fn set(service: &str, key: &str, value: &str) -> Result<(), String> {
+    #[cfg(target_os = "linux")]
+    if !fallback::keychain_available(service) {
+        return fallback::set(key, value);
+    }
+    entry(service, key)?
+        .set_password(value)
+        .map_err(|e| keychain_error("save the secret", e))
+}
+
+fn get(service: &str, key: &str) -> Result<Option<String>, String> {
+    #[cfg(target_os = "linux")]
+    if !fallback::keychain_available(service) {
+        return fallback::get(key);
+    }
+    match entry(service, key)?.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(keychain_error("read the secret", e)),
+    }
+}
+
+fn delete(service: &str, key: &str) -> Result<(), String> {
+    #[cfg(target_os = "linux")]
+    if !fallback::keychain_available(service) {
+        return fallback::delete(key);
+    }
+    match entry(service, key)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(keychain_error("delete the secret", e)),
+    }
+}
+
+/// Run a keychain call off the async runtime, since it may block on a
+/// prompt.
+async fn blocking<T: Send + 'static>(
+    call: impl FnOnce() -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    tauri::async_runtime::spawn_blocking(call)
+        .await
+        .map_err(|e| format!("Keychain task failed: {}", e))?
+}
+
+#[tauri::command]
+pub async fn secret_store_set(
+    app: tauri::AppHandle,
+    key: String,
+    value: String,
+) -> Result<(), String> {
+    validate_key(&key)?;
+    let value = Zeroizing::new(value);
+    let service = service(&app);
+    blocking(move || {
+        set(&service, &key, &value)?;
+        update_index(|keys| {
+            keys.insert(key);
+        })
+    })
+    .await
+}
+
+/// The value stored under `key`, or `None` if there is none.
+#[tauri::command]
+pub async fn secret_store_get(
+    app: tauri::AppHandle,
+    key: String,
+) -> Result<Option<String>, String> {
+    validate_key(&key)?;
+    let service = service(&app);
+    blocking(move || get(&service, &key)).await
+}
+
+#[tauri::command]
+pub async fn secret_store_delete(app: tauri::AppHandle, key: String) -> Result<(), String> {
+    validate_key(&key)?;
+    let service = service(&app);
+    blocking(move || {
+        delete(&service, &key)?;
+        update_index(|keys| {
+            keys.remove(&key);
+        })
+    })
+    .await
+}
+
+/// Names of the stored secrets, never their values.
+#[tauri::command]
+pub fn secret_store_list() -> Vec<String> {
+    read_index().into_iter().collect()
+}
+
+/// Without a Secret Service (e.g. a bare window manager), secrets are kept
+/// in a file encrypted with a key stored beside it. Both are readable only
+/// by the user, so this guards against backups and copies of the file, not
+/// against other programs the user runs.
+#[cfg(target_os = "linux")]
+mod fallback {
+    use std::collections::BTreeMap;
+    use std::fs::{self, File};
+    use std::io::{self, ErrorKind, Write};
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::path::{Path, PathBuf};
+    use std::sync::{Mutex, OnceLock};
+
+    use chacha20poly1305::aead::{Aead, KeyInit};
+    use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+    use keyring::Entry;
+    use log::warn;
+    use rand::rngs::OsRng;
+    use rand::TryRngCore;
+    use zeroize::Zeroizing;
+
+    const SECRETS_FILE: &str = "user-secrets.enc.json";
+    const KEY_FILE: &str = "user-secrets.key";
+    const KEY_LENGTH: usize = 32;
+    const NONCE_LENGTH: usize = 24;
+
+    /// Looked up once to see whether the Secret Service answers.
+    const PROBE_KEY: &str = "discobot-probe";
+
+    /// Serializes read-modify-write cycles of the secrets file.
+    static FILE_LOCK: Mutex<()> = Mutex::new(());
+
+    pub fn keychain_available(service: &str) -> bool {
+        static AVAILABLE: OnceLock<bool> = OnceLock::new();
+        *AVAILABLE.get_or_init(|| {
+            match Entry::new(service, PROBE_KEY).and_then(|entry| entry.get_password()) {
+                Ok(_) | Err(keyring::Error::NoEntry) => true,
+                Err(e) => {
+                    warn!(
+                        "Secret Service unavailable ({}), keeping secrets in an encrypted file",
+                        e
+                    );
+                    false
+                }
+            }
+        })
+    }
+
+    fn get_dir() -> Result<PathBuf, String> {
+        let data_dir =
+            dirs::data_dir().ok_or_else(|| "Could not determine data directory".to_string())?;
+        let dir = data_dir.join("discobot");
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create data directory: {}", e))?;
+        Ok(dir)
+    }
+
+    fn write_private(path: &Path, contents: &[u8]) -> Result<(), String> {
+        File::options()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)
+            .and_then(|mut file| file.write_all(contents))
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    /// The file's key, created on first use. Called with `FILE_LOCK` held,
+    /// and the key file is only ever created new, so two first writes (even
+    /// from two copies of the app) can't each end up with their own key.
+    fn cipher() -> Result<XChaCha20Poly1305, String> {
+        let path = get_dir()?.join(KEY_FILE);
+        let key = match read_key(&path)? {
+            Some(key) => key,
+            None => match create_key(&path) {
+                Ok(key) => key,
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    read_key(&path)?.ok_or_else(|| format!("{} disappeared", path.display()))?
+                }
+                Err(e) => return Err(format!("Failed to create {}: {}", path.display(), e)),
+            },
+        };
+        Ok(XChaCha20Poly1305::new(Key::from_slice(&key)))
+    }
+
+    fn read_key(path: &Path) -> Result<Option<Zeroizing<Vec<u8>>>, String> {
+        match fs::read(path) {
+            Ok(key) if key.len() == KEY_LENGTH => Ok(Some(Zeroizing::new(key))),
+            Ok(_) => Err(format!("{} is corrupt", path.display())),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+
+    fn create_key(path: &Path) -> io::Result<Zeroizing<Vec<u8>>> {
+        let mut key = Zeroizing::new(vec![0u8; KEY_LENGTH]);
+        OsRng.try_fill_bytes(&mut key).map_err(io::Error::other)?;
+        File::options()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(path)?
+            .write_all(&key)?;
+        Ok(key)
+    }
+
+    /// Hex-encoded nonce followed by ciphertext, by key name.
+    fn read() -> Result<BTreeMap<String, String>, String> {
+        let path = get_dir()?.join(SECRETS_FILE);
+        match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| format!("Failed to parse {}: {}", path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+
+    fn write(secrets: &BTreeMap<String, String>) -> Result<(), String> {
+        let contents = serde_json::to_string_pretty(secrets)
+            .map_err(|e| format!("Failed to serialize secrets: {}", e))?;
+        write_private(&get_dir()?.join(SECRETS_FILE), contents.as_bytes())
+    }
+
+    pub fn set(key: &str, value: &str) -> Result<(), String> {
+        let mut nonce = [0u8; NONCE_LENGTH];
+        OsRng
+            .try_fill_bytes(&mut nonce)
+            .map_err(|e| format!("Failed to generate a nonce: {}", e))?;
+
+        let _guard = FILE_LOCK.lock().unwrap();
+        let ciphertext = cipher()?
+            .encrypt(XNonce::from_slice(&nonce), value.as_bytes())
+            .map_err(|_| "Failed to encrypt the secret".to_string())?;
+        let mut secrets = read()?;
+        secrets.insert(
+            key.to_string(),
+            format!("{}{}", hex::encode(nonce), hex::encode(ciphertext)),
+        );
+        write(&secrets)
+    }
+
+    pub fn get(key: &str) -> Result<Option<String>, String> {
+        let (sealed, cipher) = {
+            let _guard = FILE_LOCK.lock().unwrap();
+            match read()?.remove(key) {
+                Some(sealed) => (sealed, cipher()?),
+                None => return Ok(None),
+            }
+        };
+        let bytes = hex::decode(sealed).map_err(|_| "Stored secret is corrupt".to_string())?;
+        if bytes.len() < NONCE_LENGTH {
+            return Err("Stored secret is corrupt".to_string());
+        }
+        let (nonce, ciphertext) = bytes.split_at(NONCE_LENGTH);
+        let plaintext = Zeroizing::new(
+            cipher
+                .decrypt(XNonce::from_slice(nonce), ciphertext)
+                .map_err(|_| "Failed to decrypt the secret".to_string())?,
+        );
+        String::from_utf8(plaintext.to_vec())
+            .map(Some)
+            .map_err(|_| "Stored secret is corrupt".to_string())
+    }
+
+    pub fn delete(key: &str) -> Result<(), String> {
+        let _guard = FILE_LOCK.lock().unwrap();
+        let mut secrets = read()?;
+        if secrets.remove(key).is_some() {
+            write(&secrets)?;
+        }
+        Ok(())
+    }
+}
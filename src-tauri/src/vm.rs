@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use log::{info, warn};
+use sha2::{Digest, Sha256};
+
+/// Written next to the bundled VM files by `scripts/extract-vz-image.mjs`,
+/// mapping each file name to its hex-encoded SHA-256 digest.
+const MANIFEST_FILE: &str = "manifest.json";
+
+const KERNEL_FILE: &str = "vmlinux";
+const ROOTFS_FILE: &str = "discobot-rootfs.squashfs";
+
+/// Where a platform's sandbox VM resources are bundled, and the variables
+/// that pass them to the server.
+pub struct VmPlatform {
+    name: &'static str,
+    /// Directory under the app's resource directory.
+    dir: &'static str,
+    kernel_env: &'static str,
+    rootfs_env: &'static str,
+}
+
+/// Virtualization.framework.
+#[cfg(target_os = "macos")]
+pub const PLATFORM: VmPlatform = VmPlatform {
+    name: "VZ",
+    dir: "vz",
+    kernel_env: "VZ_KERNEL_PATH",
+    rootfs_env: "VZ_BASE_DISK_PATH",
+};
+
+/// The kernel and rootfs the sandbox VM should boot, as environment variables
+/// for the server: explicit overrides win, otherwise the bundled files if
+/// they pass verification. Files left unset are downloaded by the server.
+pub fn resolve_vm_resources(
+    platform: &VmPlatform,
+    resource_dir: Option<PathBuf>,
+    kernel_setting: Option<&str>,
+    rootfs_setting: Option<&str>,
+) -> Result<Vec<(&'static str, PathBuf)>, String> {
+    let mut kernel = resolve_override(platform.kernel_env, kernel_setting)?;
+    let mut rootfs = resolve_override(platform.rootfs_env, rootfs_setting)?;
+
+    if kernel.is_none() || rootfs.is_none() {
+        if let Some(dir) = resource_dir.map(|dir| dir.join(platform.dir)) {
+            // Only verify the bundled files we're actually going to use
+            let mut needed = Vec::new();
+            if kernel.is_none() {
+                needed.push(KERNEL_FILE);
+            }
+            if rootfs.is_none() {
+                needed.push(ROOTFS_FILE);
+            }
+
+            if !needed.iter().all(|file| dir.join(file).exists()) {
+                info!(
+                    "No bundled {} resources found, will download from registry",
+                    platform.name
+                );
+            } else if let Err(e) = verify_resources(&dir, &needed) {
+                warn!(
+                    "Bundled {} resources failed verification: {}",
+                    platform.name, e
+                );
+                warn!(
+                    "Ignoring bundled {} resources, will download from registry",
+                    platform.name
+                );
+            } else {
+                info!("Found bundled {} resources", platform.name);
+                kernel.get_or_insert_with(|| dir.join(KERNEL_FILE));
+                rootfs.get_or_insert_with(|| dir.join(ROOTFS_FILE));
+            }
+        }
+    }
+
+    let mut env = Vec::new();
+    if let Some(kernel) = kernel {
+        info!("VM kernel: {}", kernel.display());
+        env.push((platform.kernel_env, kernel));
+    }
+    if let Some(rootfs) = rootfs {
+        info!("VM rootfs: {}", rootfs.display());
+        env.push((platform.rootfs_env, rootfs));
+    }
+    Ok(env)
+}
+
+/// Check that every file in `dir` matches the digest recorded in its manifest.
+///
+/// Returns an error describing the first missing entry or mismatch, so a
+/// partially copied or corrupted image is never handed to the server.
+fn verify_resources(dir: &Path, files: &[&str]) -> Result<(), String> {
+    let manifest_path = dir.join(MANIFEST_FILE);
+    let contents = fs::read_to_string(&manifest_path)
+        .map_err(|e| format!("Failed to read {}: {}", manifest_path.display(), e))?;
+    let manifest: HashMap<String, String> = serde_json::from_str(&contents)
+        .map_err(|e| format!("Invalid {}: {}", manifest_path.display(), e))?;
+
+    for file in files {
+        let expected = manifest
+            .get(*file)
+            .ok_or_else(|| format!("{} is not listed in {}", file, MANIFEST_FILE))?;
+        let actual = sha256_file(&dir.join(file))?;
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(format!(
+                "Checksum mismatch for {}: expected {}, got {}",
+                file, expected, actual
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn sha256_file(path: &Path) -> Result<String, String> {
+    let mut file =
+        fs::File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Resolve a user-provided VM resource path from `env_var` or the settings file.
+///
+/// A configured path that doesn't exist is an error rather than being silently
+/// ignored, so typos surface at startup instead of booting the bundled image.
+fn resolve_override(env_var: &str, setting: Option<&str>) -> Result<Option<PathBuf>, String> {
+    let (source, value) = match std::env::var(env_var) {
+        Ok(value) if !value.is_empty() => (env_var, value),
+        _ => match setting {
+            Some(value) if !value.is_empty() => ("settings", value.to_string()),
+            _ => return Ok(None),
+        },
+    };
+
+    let path = PathBuf::from(value);
+    if !path.exists() {
+        return Err(format!(
+            "VM resource override from {} does not exist: {}",
+            source,
+            path.display()
+        ));
+    }
+    info!(
+        "Using VM resource override from {}: {}",
+        source,
+        path.display()
+    );
+    Ok(Some(path))
+}
@@ -0,0 +1,101 @@
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use log::{info, warn};
+use serde::Serialize;
+use tauri::{Emitter, Manager};
+
+use crate::settings::Settings;
+use crate::{health, respawn_server, ServerState};
+
+/// How often the monitor thread compares the clocks.
+const TICK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Wall-clock time running ahead of monotonic time by at least this much
+/// means the system was asleep.
+const SLEEP_THRESHOLD: Duration = Duration::from_secs(30);
+
+/// Health probes attempted after wake before the server is considered wedged.
+const WAKE_PROBE_ATTEMPTS: u32 = 3;
+const WAKE_PROBE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Emitted after the server was restarted automatically.
+const SERVER_RESTARTED_EVENT: &str = "server-restarted";
+
+/// Emitted when the server is unresponsive and the user should decide
+/// whether to restart it (see `Settings::restart_on_wake`).
+const SERVER_UNHEALTHY_EVENT: &str = "server-unhealthy";
+
+#[derive(Clone, Serialize)]
+struct RecoveryPayload {
+    reason: &'static str,
+}
+
+/// Watch for the system waking from sleep and re-check the server afterwards.
+///
+/// Rather than subscribing to platform power notifications, this compares
+/// wall-clock time against the monotonic clock, which doesn't advance while
+/// the machine is suspended. The same heuristic works on macOS and Linux.
+pub fn spawn_wake_monitor(app: tauri::AppHandle) {
+    thread::spawn(move || {
+        let mut last_instant = Instant::now();
+        let mut last_wall = SystemTime::now();
+        loop {
+            thread::sleep(TICK_INTERVAL);
+
+            let now_instant = Instant::now();
+            let now_wall = SystemTime::now();
+            let monotonic = now_instant.duration_since(last_instant);
+            let wall = now_wall.duration_since(last_wall).unwrap_or_default();
+            last_instant = now_instant;
+            last_wall = now_wall;
+
+            let slept = wall.saturating_sub(monotonic);
+            if slept >= SLEEP_THRESHOLD {
+                info!(
+                    "System woke after ~{}s asleep, checking server health",
+                    slept.as_secs()
+                );
+                tauri::async_runtime::spawn(recover_after_wake(app.clone()));
+            }
+        }
+    });
+}
+
+async fn recover_after_wake(app: tauri::AppHandle) {
+    let (endpoint, external) = {
+        let state = app.state::<Mutex<ServerState>>();
+        let state = state.lock().unwrap();
+        if state.shutting_down || (state.process.is_none() && !state.external) {
+            return;
+        }
+        (state.endpoint(), state.external)
+    };
+
+    for attempt in 0..WAKE_PROBE_ATTEMPTS {
+        if attempt > 0 {
+            tokio::time::sleep(WAKE_PROBE_INTERVAL).await;
+        }
+        if health::check_health(&endpoint).await {
+            return;
+        }
+    }
+
+    // An external server isn't ours to restart.
+    let restart_on_wake = app
+        .state::<Mutex<Settings>>()
+        .lock()
+        .unwrap()
+        .restart_on_wake;
+    if external || !restart_on_wake {
+        warn!("Server is unresponsive after wake, asking the user to restart it");
+        let _ = app.emit(SERVER_UNHEALTHY_EVENT, RecoveryPayload { reason: "wake" });
+        return;
+    }
+
+    warn!("Server is unresponsive after wake, restarting it");
+    let handle = app.clone();
+    let _ = tauri::async_runtime::spawn_blocking(move || respawn_server(&handle)).await;
+    let _ = app.emit(SERVER_RESTARTED_EVENT, RecoveryPayload { reason: "wake" });
+}
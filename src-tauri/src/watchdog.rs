@@ -0,0 +1,88 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use log::warn;
+use serde::Serialize;
+use tauri::{Emitter, Manager};
+
+use crate::settings::Settings;
+use crate::{health, respawn_server, ServerState, ServerStatus};
+
+/// How often to look again while the watchdog is turned off.
+const DISABLED_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Emitted just before an unresponsive sidecar is force-restarted.
+const SERVER_UNRESPONSIVE_EVENT: &str = "server-unresponsive";
+
+#[derive(Clone, Serialize)]
+struct Unresponsive {
+    failures: u32,
+}
+
+/// Probe the running sidecar's `/health` every
+/// `Settings::health_check_interval_secs` and restart it after
+/// `health_check_failures` failures in a row, for a process that's alive but
+/// wedged, which the supervisor can't see. Failures only count against one
+/// sidecar, and nothing is probed while the server is starting, stopped or
+/// suspended, so a manual stop or restart is never undone.
+pub fn spawn_watchdog(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        // Generation of the sidecar the failures were counted against
+        let mut failures = (0, 0u32);
+        loop {
+            let (interval, threshold) = {
+                let settings = app.state::<Mutex<Settings>>();
+                let settings = settings.lock().unwrap();
+                (
+                    settings.health_check_interval_secs,
+                    settings.health_check_failures.max(1),
+                )
+            };
+            let Some(interval) = interval.filter(|secs| *secs > 0) else {
+                tokio::time::sleep(DISABLED_POLL_INTERVAL).await;
+                continue;
+            };
+            tokio::time::sleep(Duration::from_secs(interval)).await;
+
+            let Some((endpoint, generation)) = probe_target(&app) else {
+                continue;
+            };
+            if health::check_health(&endpoint).await {
+                failures = (generation, 0);
+                continue;
+            }
+            let count = if failures.0 == generation {
+                failures.1 + 1
+            } else {
+                1
+            };
+            failures = (generation, count);
+            warn!("Server health check failed ({} of {})", count, threshold);
+            if count < threshold {
+                continue;
+            }
+
+            // The user may have restarted or stopped it during the probe
+            if probe_target(&app).is_none_or(|(_, current)| current != generation) {
+                continue;
+            }
+            warn!("Server is unresponsive, restarting it");
+            let _ = app.emit(SERVER_UNRESPONSIVE_EVENT, Unresponsive { failures: count });
+            let handle = app.clone();
+            let _ = tauri::async_runtime::spawn_blocking(move || respawn_server(&handle)).await;
+        }
+    });
+}
+
+/// Where to probe the current sidecar and its generation, or `None` unless
+/// it's a running sidecar of this app.
+fn probe_target(app: &tauri::AppHandle) -> Option<(health::Endpoint, u64)> {
+    let state = app.state::<Mutex<ServerState>>();
+    let state = state.lock().unwrap();
+    let running = matches!(state.status, ServerStatus::Running { .. })
+        && state.process.is_some()
+        && state.startup.is_none()
+        && !state.external
+        && !state.shutting_down;
+    running.then(|| (state.endpoint(), state.generation))
+}